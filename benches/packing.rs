@@ -0,0 +1,78 @@
+/*
+    Compares the bin-packing heuristics on random inputs: how much of the containers they fill
+    and how long they take. The inputs come from a seeded RNG, so every run packs the same items.
+
+    cargo bench --bench packing
+ */
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_book::rectangle::packing::{pack, Heuristic};
+use rust_book::rectangle::Rectangle;
+use std::time::Instant;
+
+struct Scenario {
+    name: &'static str,
+    items: usize,
+    item: fn(&mut StdRng) -> Rectangle,
+    containers: usize,
+    container: Rectangle,
+}
+
+fn scenarios() -> [Scenario; 3] {
+    [
+        Scenario {
+            name: "many small items",
+            items: 2_000,
+            item: |rng| Rectangle::new(rng.gen_range(1..=16), rng.gen_range(1..=16)),
+            containers: 8,
+            container: Rectangle::square(128),
+        },
+        Scenario {
+            name: "mixed sizes",
+            items: 500,
+            item: |rng| Rectangle::new(rng.gen_range(1..=64), rng.gen_range(1..=64)),
+            containers: 16,
+            container: Rectangle::square(256),
+        },
+        Scenario {
+            name: "long thin items",
+            items: 400,
+            item: |rng| Rectangle::new(rng.gen_range(50..=100), rng.gen_range(1..=10)),
+            containers: 8,
+            container: Rectangle::square(200),
+        },
+    ]
+}
+
+fn main() {
+    const RUNS: u32 = 5;
+
+    for scenario in scenarios() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let items: Vec<Rectangle> = (0..scenario.items).map(|_| (scenario.item)(&mut rng)).collect();
+        let containers = vec![scenario.container; scenario.containers];
+        println!("{} ({} items, {} containers)", scenario.name, scenario.items, scenario.containers);
+
+        for heuristic in Heuristic::ALL {
+            for allow_rotation in [false, true] {
+                let started = Instant::now();
+                let mut packing = pack(&items, &containers, heuristic, allow_rotation);
+                for _ in 1..RUNS {
+                    packing = pack(&items, &containers, heuristic, allow_rotation);
+                }
+                let elapsed = started.elapsed() / RUNS;
+
+                let used = packing.utilisation().iter().filter(|entry| entry.items > 0).count();
+                println!(
+                    "  {:<10} rotation {:<5}  {:>2} containers  {:>5.1}% used  {:>4} unplaced  {:>10.2?}",
+                    heuristic.to_string(),
+                    allow_rotation,
+                    used,
+                    packing.total_utilisation() * 100.0,
+                    packing.unplaced.len(),
+                    elapsed
+                );
+            }
+        }
+    }
+}
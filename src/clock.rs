@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/*
+    Time-based limits need to know what time it is, but tests that sleep are slow and flaky.
+    So the rate limiters and the shirt reservations ask a Clock instead of calling Instant::now
+    themselves: SystemClock is the real thing, and ManualClock only moves when a test tells it to.
+
+    A Clock reports the time elapsed since some fixed starting point; only differences matter.
+ */
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that starts at zero and only moves forward when `advance` is called.
+/// It can be shared between threads, so one thread can move time while others read it.
+#[derive(Default)]
+pub struct ManualClock {
+    now: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
@@ -1,4 +1,11 @@
-use std::io;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::num::ParseIntError;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
 //use anyhow::anyhow; // Only use anyhow! macro if no default implementation is already provided by the crate
 /*
     The logic behind the anyhow crate is that it provides its own error type. This type has
@@ -12,18 +19,107 @@ use std::io;
 use rand::Rng; // The Rng trait defines methods that random number generators implement
 use std::cmp::Ordering; // enum with the variants Less, Greater, and Equal. These are the three outcomes that are possible when you compare two values.
 
-const RANGE_UPPER_LIMIT: i32 = 100;
+pub mod high_scores;
+pub mod messages;
+pub mod network;
+pub mod replay;
+pub mod settings;
+pub mod solver;
+pub mod variants;
+
+pub use self::high_scores::{HighScores, Score};
+pub use self::messages::{Key, Locale};
+pub use self::replay::{play_back, record, start_recorded_game, Replay};
+pub use self::settings::{Difficulty, Settings, DEFAULT_RANGE};
+pub use self::solver::{benchmark, solve, Benchmark, Strategy};
+pub use self::variants::{play_variant, Classic, Variant};
 
 pub struct Guess {
     value: i32,
 }
+
+/// Why a line of input is not a valid guess.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuessError {
+    /// The input is not a whole number.
+    Parse { input: String, source: ParseIntError },
+    OutOfRange { value: i32, min: i32, max: i32 },
+    /// Bulls and cows: the input is not a code of `length` different digits.
+    Code { input: String, length: usize },
+    /// Reverse mode: the input is not one of the replies the computer understands.
+    Reply { input: String },
+    /// The game was already won or lost.
+    GameOver,
+}
+
+impl fmt::Display for GuessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Locale::English.guess_error(self))
+    }
+}
+
+impl Error for GuessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GuessError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/*
+    Parsing and validating in one step: "42".parse::<Guess>() trims the surrounding whitespace
+    (including the newline read_line leaves in the buffer) and then applies Guess::try_new.
+    Both only know the classic 1..=100 range; a game with other settings checks its guesses
+    itself, in Game::validate and Game::guess.
+ */
+impl FromStr for Guess {
+    type Err = GuessError;
+
+    fn from_str(input: &str) -> Result<Guess, GuessError> {
+        Guess::parse_within(input, &DEFAULT_RANGE)
+    }
+}
 impl Guess {
     pub fn new(value: i32) -> Guess {
-        if value < 1 || value > 100 {
-            panic!("Guess value must be between 1 and 100, got {}.", value);
+        match Guess::try_new(value) {
+            Ok(guess) => guess,
+            Err(_) => panic!(
+                "Guess value must be between {} and {}, got {}.",
+                DEFAULT_RANGE.start(),
+                DEFAULT_RANGE.end(),
+                value
+            ),
         }
+    }
 
-        Guess { value }
+    /// Validates `value` against `DEFAULT_RANGE` without panicking, for input that comes from users.
+    pub fn try_new(value: i32) -> Result<Guess, GuessError> {
+        Guess::within(value, &DEFAULT_RANGE)
+    }
+
+    /// Validates `value` against the range of a particular game.
+    pub fn within(value: i32, range: &RangeInclusive<i32>) -> Result<Guess, GuessError> {
+        if !range.contains(&value) {
+            return Err(GuessError::OutOfRange {
+                value,
+                min: *range.start(),
+                max: *range.end(),
+            });
+        }
+
+        Ok(Guess { value })
+    }
+
+    /// Parses a line of input and validates it against `range`.
+    pub fn parse_within(input: &str, range: &RangeInclusive<i32>) -> Result<Guess, GuessError> {
+        let input = input.trim();
+        let value = input.parse::<i32>().map_err(|source| GuessError::Parse {
+            input: String::from(input),
+            source,
+        })?;
+
+        Guess::within(value, range)
     }
 
     /*
@@ -38,38 +134,162 @@ impl Guess {
     }
 }
 
+/// What the game answers to a guess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    TooSmall,
+    TooBig,
+    Correct,
+}
+
+/// How a finished game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Won,
+    /// The player ran out of attempts.
+    Lost,
+}
+
+/*
+    The game engine only knows the secret number, its settings and how many guesses were made.
+    It does no I/O and takes its random number generator from the caller, so a seeded generator
+    (for example rand::rngs::StdRng::seed_from_u64) always produces the same game. Front-ends
+    such as play decide where the guesses come from and how the feedback is shown.
+ */
+pub struct Game {
+    secret_number: i32,
+    settings: Settings,
+    attempts: u32,
+    outcome: Option<Outcome>,
+}
+
+impl Game {
+    /// Picks the secret number uniformly from `range`; the player has unlimited attempts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is empty.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, range: RangeInclusive<i32>) -> Game {
+        Game::with_settings(rng, Settings::new(range, None))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `settings.range` is empty.
+    pub fn with_settings<R: Rng + ?Sized>(rng: &mut R, settings: impl Into<Settings>) -> Game {
+        let settings = settings.into();
+        // Q# style syntax for range where = indicates inclusive
+        let secret_number = rng.gen_range(settings.range.clone());
+
+        Game {
+            secret_number,
+            settings,
+            attempts: 0,
+            outcome: None,
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn range(&self) -> &RangeInclusive<i32> {
+        &self.settings.range
+    }
+
+    /// How many guesses have been made so far.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// How many guesses are left, or `None` if the number of attempts is unlimited.
+    pub fn remaining_attempts(&self) -> Option<u32> {
+        self.settings
+            .max_attempts
+            .map(|max| max.saturating_sub(self.attempts))
+    }
+
+    /// `None` while the game is still going on.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// The secret number, but only once the game is over.
+    pub fn reveal(&self) -> Option<i32> {
+        self.outcome.map(|_| self.secret_number)
+    }
+
+    /// Parses and validates a line of input against this game's range.
+    pub fn validate(&self, input: &str) -> Result<Guess, GuessError> {
+        Guess::parse_within(input, &self.settings.range)
+    }
+
+    /// Fails without counting an attempt if `guess` is outside this game's range or the game is
+    /// already over.
+    pub fn guess(&mut self, guess: i32) -> Result<Feedback, GuessError> {
+        if self.outcome.is_some() {
+            return Err(GuessError::GameOver);
+        }
+        Guess::within(guess, &self.settings.range)?;
+        self.attempts += 1;
+
+        let feedback = match guess.cmp(&self.secret_number) {
+            Ordering::Less => Feedback::TooSmall,
+            Ordering::Greater => Feedback::TooBig,
+            Ordering::Equal => Feedback::Correct,
+        };
+
+        if feedback == Feedback::Correct {
+            self.outcome = Some(Outcome::Won);
+        } else if self.remaining_attempts() == Some(0) {
+            self.outcome = Some(Outcome::Lost);
+        }
+
+        Ok(feedback)
+    }
+}
+
+/// Plays `game` interactively, reading one guess per line from `input` and writing to `output`.
+pub fn play<R: BufRead, W: Write>(game: &mut Game, input: R, output: W) -> anyhow::Result<Outcome> {
+    play_variant(&mut Classic::new(game), input, output)
+}
+
+// Games on the terminal speak the language of `LANG` unless the settings chose one.
+pub(crate) fn terminal_settings(settings: impl Into<Settings>) -> Settings {
+    let settings = settings.into();
+    match settings.locale {
+        Some(_) => settings,
+        None => settings.with_locale(Locale::from_env()),
+    }
+}
+
 pub fn start_game() -> anyhow::Result<()> {
-    println!("Guess the number!");
+    start_game_with(Settings::default())
+}
 
-    // Q# style syntax for range where = indicates inclusive
+/// Plays one game on the terminal, for example with `start_game_with(Difficulty::Hard)`.
+pub fn start_game_with(settings: impl Into<Settings>) -> anyhow::Result<()> {
     // the rand::thread_rng function that gives us the particular random number generator:
     // one that is local to the current thread of execution and is seeded by the operating system
-    let secret_number: i32 = rand::thread_rng().gen_range(1..=RANGE_UPPER_LIMIT);
-
-    loop {
-        println!("Please input your guess.");
-
-        // string type provided by the standard library that is a growable, UTF-8 encoded bit of text
-        let mut guess = String::new();
-
-        // The stdin function returns an instance of std::io::Stdin, which is a type that represents a handle to the standard input for the terminal
-        // Result is an enum, which is a type that can be in one of multiple possible states. We call each possible state a variant (Ok and Err).
-        // Returns the number of bytes in the user’s input
-        io::stdin().read_line(&mut guess)?;
-
-        // Shadowing and trimming the newline character \n that gets appended when the user hits enter after typing their input
-        // Without anyhow, the trait `From<ParseIntError>` is not implemented for `std::io::Error`
-        let guess = Guess::new(guess.trim().parse::<i32>()?);
-
-        match guess.value.cmp(&secret_number) {
-            Ordering::Less => println!("Too small. Try a bigger number!"),
-            Ordering::Greater => println!("Too big. Try a small number!"),
-            Ordering::Equal => {
-                println!("You win!");
-                break
-            }
-        }
-    }
+    let mut game = Game::with_settings(&mut rand::thread_rng(), terminal_settings(settings));
 
+    // The stdin function returns an instance of std::io::Stdin, which is a type that represents a handle to the standard input for the terminal
+    play(&mut game, io::stdin().lock(), io::stdout())?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Plays one game at `difficulty` on the terminal, records a win in the high-score file at
+/// `scores` and shows the top 10 for that difficulty.
+pub fn start_ranked_game<P: AsRef<Path>>(player: &str, difficulty: Difficulty, scores: P) -> anyhow::Result<()> {
+    let mut game = Game::with_settings(&mut rand::thread_rng(), terminal_settings(difficulty));
+    let started = Instant::now();
+    let outcome = play(&mut game, io::stdin().lock(), io::stdout())?;
+
+    let mut table = HighScores::load(&scores)?;
+    if outcome == Outcome::Won {
+        table.record(Score::new(player, game.attempts(), started.elapsed(), difficulty));
+        table.save(&scores)?;
+    }
+    table.show(difficulty, 10, io::stdout())?;
+    Ok(())
+}
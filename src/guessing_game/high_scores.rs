@@ -0,0 +1,388 @@
+use super::Difficulty;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HEADER: &str = "# guessing game high scores v1";
+// Who a score without a player name is recorded for; the file has no way to store an empty name.
+const ANONYMOUS: &str = "anonymous";
+
+/// One won game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub player: String,
+    pub attempts: u32,
+    pub duration: Duration,
+    pub difficulty: Difficulty,
+    pub date: SystemTime,
+}
+
+impl Score {
+    /// Builds a score dated now.
+    pub fn new(player: &str, attempts: u32, duration: Duration, difficulty: Difficulty) -> Score {
+        Score {
+            player: String::from(player),
+            attempts,
+            duration,
+            difficulty,
+            date: SystemTime::now(),
+        }
+    }
+
+    fn to_line(&self) -> String {
+        // Tabs and newlines would break the line format, so names can't contain them.
+        let mut player: String = self
+            .player
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        // A name like "#1 fan" would read back as a comment, so it gets a backslash in front.
+        if player.starts_with(['#', '\\']) {
+            player.insert(0, '\\');
+        }
+
+        format!(
+            "{player}\t{}\t{}\t{}\t{}",
+            self.attempts,
+            self.duration.as_millis(),
+            self.difficulty,
+            seconds_since_epoch(self.date)
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Score> {
+        let mut fields = line.split('\t');
+        let player = fields.next()?;
+        let player = player.strip_prefix('\\').unwrap_or(player);
+        let attempts = fields.next()?.parse().ok()?;
+        let millis = fields.next()?.parse().ok()?;
+        let difficulty = fields.next()?.parse().ok()?;
+        let seconds = fields.next()?.parse().ok()?;
+        if player.is_empty() || fields.next().is_some() {
+            return None;
+        }
+
+        Some(Score {
+            player: String::from(player),
+            attempts,
+            duration: Duration::from_millis(millis),
+            difficulty,
+            date: UNIX_EPOCH + Duration::from_secs(seconds),
+        })
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+/*
+    Formats the UTC calendar date of `time` as YYYY-MM-DD. This is the days-to-civil algorithm
+    from Howard Hinnant's "chrono-Compatible Low-Level Date Algorithms", which counts in 400-year
+    eras that start on March 1st so that leap days fall at the end of each year.
+ */
+fn calendar_date(time: SystemTime) -> String {
+    let days = (seconds_since_epoch(time) / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/*
+    The table is stored as a small text file, one score per line with tab-separated fields:
+
+        <player>  <attempts>  <duration in ms>  <difficulty>  <date in seconds since epoch>
+
+    The first line is a header naming the format, other lines starting with # are comments, and
+    a player whose name starts with # or \ is written with a \ in front.
+
+    A missing file is an empty table. A file that doesn't start with the header is not a table of
+    ours (or one from a later version), and loading it fails rather than letting the next save
+    overwrite it. Past the header, lines that can't be read (a corrupt or hand-edited file) are
+    skipped and counted, so one bad line does not cost the player their other scores. Saving
+    writes a temporary file next to the real one and renames it over the original, so a crash
+    mid-write leaves the old table intact; every save gets its own temporary file, so two saves
+    at once don't write into the same one.
+ */
+#[derive(Debug, Default)]
+pub struct HighScores {
+    scores: Vec<Score>,
+    skipped: usize,
+}
+
+impl HighScores {
+    pub fn new() -> HighScores {
+        HighScores::default()
+    }
+
+    /// Reads the table at `path`. Fails on I/O errors other than a missing file, and with
+    /// `InvalidData` if the file doesn't start with the high-score header.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<HighScores> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HighScores::new()),
+            Err(error) => return Err(error),
+        };
+
+        let text = String::from_utf8_lossy(&bytes);
+        let mut lines = text.lines();
+        match lines.next() {
+            None => return Ok(HighScores::new()),
+            Some(HEADER) => {}
+            Some(header) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("not a high-score file: expected `{HEADER}`, found `{header}`"),
+                ))
+            }
+        }
+
+        let mut table = HighScores::new();
+        for line in lines {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match Score::from_line(line) {
+                Some(score) => table.scores.push(score),
+                None => table.skipped += 1,
+            }
+        }
+        Ok(table)
+    }
+
+    /// Writes the table to `path` atomically.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        static SAVES: AtomicU64 = AtomicU64::new(0);
+        let path = path.as_ref();
+        let mut temporary = PathBuf::from(path);
+        let save = SAVES.fetch_add(1, Ordering::Relaxed);
+        temporary.as_mut_os_string().push(format!(".{}.{save}.tmp", process::id()));
+
+        let mut file = File::create(&temporary)?;
+        writeln!(file, "{HEADER}")?;
+        for score in &self.scores {
+            writeln!(file, "{}", score.to_line())?;
+        }
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temporary, path)
+    }
+
+    /// Adds `score` to the table; a score without a player name is recorded as "anonymous".
+    pub fn record(&mut self, mut score: Score) {
+        if score.player.is_empty() {
+            score.player = String::from(ANONYMOUS);
+        }
+        self.scores.push(score);
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// How many lines of the loaded file could not be read.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /*
+        Fewer attempts rank higher. Ties go to the faster game, and if that ties too,
+        to whoever set the score first.
+     */
+    /// The best `n` scores for `difficulty`, best first.
+    pub fn top(&self, difficulty: Difficulty, n: usize) -> Vec<&Score> {
+        let mut scores: Vec<&Score> = self
+            .scores
+            .iter()
+            .filter(|score| score.difficulty == difficulty)
+            .collect();
+        scores.sort_by_key(|score| (score.attempts, score.duration, score.date));
+        scores.truncate(n);
+        scores
+    }
+
+    /// Writes the best `n` scores for `difficulty` as a numbered table.
+    pub fn show<W: Write>(&self, difficulty: Difficulty, n: usize, mut output: W) -> io::Result<()> {
+        writeln!(output, "High scores ({difficulty}):")?;
+        let top = self.top(difficulty, n);
+        if top.is_empty() {
+            writeln!(output, "  none yet")?;
+        }
+        for (rank, score) in top.iter().enumerate() {
+            writeln!(output, "{:>3}. {score}", rank + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<16} {:>3} attempts {:>8.1}s  {}",
+            self.player,
+            self.attempts,
+            self.duration.as_secs_f64(),
+            calendar_date(self.date)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn scratch_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust_book_{name}_{}.txt", process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn score(player: &str, attempts: u32, seconds: u64, difficulty: Difficulty) -> Score {
+        Score {
+            player: String::from(player),
+            attempts,
+            duration: Duration::from_secs(seconds),
+            difficulty,
+            date: UNIX_EPOCH + Duration::from_secs(1_700_000_000 + seconds),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let path = scratch_file("high_scores_round_trip");
+        let mut table = HighScores::load(&path).unwrap();
+        assert!(table.is_empty());
+
+        table.record(score("Ferris", 5, 30, Difficulty::Normal));
+        table.record(score("tab\tname", 9, 12, Difficulty::Hard));
+        table.record(score("#1 fan", 3, 40, Difficulty::Easy));
+        table.record(score("\\o/", 4, 40, Difficulty::Easy));
+        table.save(&path).unwrap();
+
+        let loaded = HighScores::load(&path).unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(loaded.skipped(), 0);
+        assert_eq!(loaded.top(Difficulty::Normal, 1), vec![&score("Ferris", 5, 30, Difficulty::Normal)]);
+        assert_eq!(loaded.top(Difficulty::Hard, 1)[0].player, "tab name");
+        let easy: Vec<&str> = loaded.top(Difficulty::Easy, 2).iter().map(|score| score.player.as_str()).collect();
+        assert_eq!(easy, vec!["#1 fan", "\\o/"]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn records_nameless_scores_as_anonymous() {
+        let path = scratch_file("high_scores_anonymous");
+        let mut table = HighScores::new();
+        table.record(score("", 6, 25, Difficulty::Normal));
+        table.save(&path).unwrap();
+
+        let loaded = HighScores::load(&path).unwrap();
+        assert_eq!(loaded.skipped(), 0);
+        assert_eq!(loaded.top(Difficulty::Normal, 1), vec![&score("anonymous", 6, 25, Difficulty::Normal)]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn ranks_by_attempts_then_duration_per_difficulty() {
+        let mut table = HighScores::new();
+        table.record(score("slow", 4, 90, Difficulty::Easy));
+        table.record(score("fast", 4, 20, Difficulty::Easy));
+        table.record(score("lucky", 1, 50, Difficulty::Easy));
+        table.record(score("other", 1, 1, Difficulty::Hard));
+
+        let names: Vec<&str> = table
+            .top(Difficulty::Easy, 10)
+            .iter()
+            .map(|score| score.player.as_str())
+            .collect();
+        assert_eq!(names, vec!["lucky", "fast", "slow"]);
+        assert_eq!(table.top(Difficulty::Easy, 2).len(), 2);
+
+        let mut shown = vec![];
+        table.show(Difficulty::Hard, 5, &mut shown).unwrap();
+        assert_eq!(
+            String::from_utf8(shown).unwrap(),
+            "High scores (hard):\n  1. other              1 attempts      1.0s  2023-11-14\n"
+        );
+    }
+
+    #[test]
+    fn survives_a_corrupt_file() {
+        let path = scratch_file("high_scores_corrupt");
+        let mut bytes = b"# guessing game high scores v1\n\
+                          Ferris\t5\t30000\tnormal\t1700000000\n\
+                          garbage without tabs\n\
+                          Mallory\tmany\t1\thard\t1\n"
+            .to_vec();
+        bytes.extend_from_slice(&[0xff, 0xfe, b'\n']);
+        fs::write(&path, bytes).unwrap();
+
+        let table = HighScores::load(&path).unwrap();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.skipped(), 3);
+
+        // Saving rewrites the file without the unreadable lines.
+        table.save(&path).unwrap();
+        assert_eq!(HighScores::load(&path).unwrap().skipped(), 0);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn refuses_files_without_the_header() {
+        let path = scratch_file("high_scores_header");
+        for contents in ["# guessing game high scores v2\n", "Ferris\t5\t30000\tnormal\t1700000000\n"] {
+            fs::write(&path, contents).unwrap();
+            let error = HighScores::load(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        fs::write(&path, "").unwrap();
+        assert!(HighScores::load(&path).unwrap().is_empty());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves_use_their_own_temporary_files() {
+        let path = scratch_file("high_scores_concurrent");
+        thread::scope(|scope| {
+            for player in ["Ferris", "Corro"] {
+                let path = &path;
+                scope.spawn(move || {
+                    let mut table = HighScores::new();
+                    table.record(score(player, 5, 30, Difficulty::Normal));
+                    for _ in 0..50 {
+                        table.save(path).unwrap();
+                    }
+                });
+            }
+        });
+
+        let table = HighScores::load(&path).unwrap();
+        assert_eq!((table.len(), table.skipped()), (1, 0));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn formats_calendar_dates() {
+        assert_eq!(calendar_date(UNIX_EPOCH), "1970-01-01");
+        assert_eq!(calendar_date(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29");
+        assert_eq!(calendar_date(UNIX_EPOCH + Duration::from_secs(1_700_000_000)), "2023-11-14");
+    }
+}
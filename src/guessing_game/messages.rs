@@ -0,0 +1,325 @@
+use super::GuessError;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/*
+    The keys are declared through this macro so that Key::ALL is generated from the same list as
+    the enum and can't leave a key out.
+ */
+macro_rules! keys {
+    ($($(#[$attribute:meta])* $key:ident,)*) => {
+        /// Every piece of text the games show the player.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($(#[$attribute])* $key,)*
+        }
+
+        impl Key {
+            /// Every key, in declaration order.
+            pub const ALL: &'static [Key] = &[$(Key::$key),*];
+        }
+    };
+}
+
+keys! {
+    Title,
+    /// Takes `{min}` and `{max}`.
+    Range,
+    /// Takes `{attempts}`.
+    Attempts,
+    Prompt,
+    TooSmall,
+    TooBig,
+    Win,
+    /// Takes `{secret}`.
+    Lose,
+    EmptyInput,
+    /// Takes `{input}`.
+    NotANumber,
+    /// Takes `{min}`, `{max}` and `{value}`.
+    OutOfRange,
+    GameOver,
+    /// Takes `{input}` and `{length}`.
+    NotACode,
+    NotAReply,
+    HotColdTitle,
+    HotColdRules,
+    Boiling,
+    Hot,
+    Warm,
+    Cool,
+    Cold,
+    Freezing,
+    BullsAndCowsTitle,
+    /// Takes `{length}`.
+    BullsAndCowsRules,
+    BullsAndCowsHint,
+    CodePrompt,
+    /// Takes `{bulls}` and `{cows}`, the counts as written by Bull or Bulls and Cow or Cows.
+    Score,
+    /// Takes `{count}`.
+    Bull,
+    /// Takes `{count}`.
+    Bulls,
+    /// Takes `{count}`.
+    Cow,
+    /// Takes `{count}`.
+    Cows,
+    /// Takes `{code}`.
+    CodeLost,
+    /// Takes `{min}` and `{max}`.
+    ReverseRules,
+    ReverseReplies,
+    /// Takes `{guess}`.
+    ReversePrompt,
+    /// Takes `{guess}` and `{attempts}`.
+    ReverseWin,
+    Contradiction,
+    /// Takes `{id}`.
+    Joined,
+    /// Takes `{winner}` and `{secret}`.
+    RaceLost,
+    /// Takes `{secret}`.
+    OutOfAttempts,
+}
+
+fn english(key: Key) -> &'static str {
+    match key {
+        Key::Title => "Guess the number!",
+        Key::Range => "The number is between {min} and {max}.",
+        Key::Attempts => "You have {attempts} attempts.",
+        Key::Prompt => "Please input your guess.",
+        Key::TooSmall => "Too small. Try a bigger number!",
+        Key::TooBig => "Too big. Try a small number!",
+        Key::Win => "You win!",
+        Key::Lose => "You lose! The number was {secret}.",
+        Key::EmptyInput => "Please type a number.",
+        Key::NotANumber => "`{input}` is not a number.",
+        Key::OutOfRange => "The guess must be between {min} and {max}, got {value}.",
+        Key::GameOver => "The game is already over.",
+        Key::NotACode => "`{input}` is not a code of {length} different digits.",
+        Key::NotAReply => "Please answer higher, lower or yes.",
+        Key::HotColdTitle => "Hot or cold?",
+        Key::HotColdRules => "The closer you get, the hotter it gets.",
+        Key::Boiling => "Boiling!",
+        Key::Hot => "Hot!",
+        Key::Warm => "Warm.",
+        Key::Cool => "Cool.",
+        Key::Cold => "Cold.",
+        Key::Freezing => "Freezing!",
+        Key::BullsAndCowsTitle => "Bulls and cows!",
+        Key::BullsAndCowsRules => "Guess the code of {length} different digits.",
+        Key::BullsAndCowsHint => "A bull is a right digit in the right place, a cow a right digit in the wrong place.",
+        Key::CodePrompt => "Please input your code.",
+        Key::Score => "{bulls}, {cows}.",
+        Key::Bull => "{count} bull",
+        Key::Bulls => "{count} bulls",
+        Key::Cow => "{count} cow",
+        Key::Cows => "{count} cows",
+        Key::CodeLost => "You lose! The code was {code}.",
+        Key::ReverseRules => "Think of a number between {min} and {max}, and I will guess it.",
+        Key::ReverseReplies => "Answer each guess with higher, lower or yes.",
+        Key::ReversePrompt => "Is it {guess}?",
+        Key::ReverseWin => "Got it: {guess} in {attempts} guesses!",
+        Key::Contradiction => "That can't be right: no number fits all of your answers.",
+        Key::Joined => "You are player {id}. Waiting for the other players...",
+        Key::RaceLost => "Player {winner} got there first. The number was {secret}.",
+        Key::OutOfAttempts => "Nobody guessed it in time. The number was {secret}.",
+    }
+}
+
+fn german(key: Key) -> &'static str {
+    match key {
+        Key::Title => "Errate die Zahl!",
+        Key::Range => "Die Zahl liegt zwischen {min} und {max}.",
+        Key::Attempts => "Du hast {attempts} Versuche.",
+        Key::Prompt => "Bitte gib deinen Tipp ein.",
+        Key::TooSmall => "Zu klein. Versuch eine größere Zahl!",
+        Key::TooBig => "Zu groß. Versuch eine kleinere Zahl!",
+        Key::Win => "Gewonnen!",
+        Key::Lose => "Verloren! Die Zahl war {secret}.",
+        Key::EmptyInput => "Bitte gib eine Zahl ein.",
+        Key::NotANumber => "`{input}` ist keine Zahl.",
+        Key::OutOfRange => "Der Tipp muss zwischen {min} und {max} liegen, nicht {value}.",
+        Key::GameOver => "Das Spiel ist schon vorbei.",
+        Key::NotACode => "`{input}` ist kein Code aus {length} verschiedenen Ziffern.",
+        Key::NotAReply => "Bitte antworte mit higher, lower oder yes.",
+        Key::HotColdTitle => "Heiß oder kalt?",
+        Key::HotColdRules => "Je näher du kommst, desto heißer wird es.",
+        Key::Boiling => "Kochend heiß!",
+        Key::Hot => "Heiß!",
+        Key::Warm => "Warm.",
+        Key::Cool => "Kühl.",
+        Key::Cold => "Kalt.",
+        Key::Freezing => "Eiskalt!",
+        Key::BullsAndCowsTitle => "Bullen und Kühe!",
+        Key::BullsAndCowsRules => "Errate den Code aus {length} verschiedenen Ziffern.",
+        Key::BullsAndCowsHint => "Ein Bulle ist eine richtige Ziffer am richtigen Platz, eine Kuh eine richtige Ziffer am falschen.",
+        Key::CodePrompt => "Bitte gib deinen Code ein.",
+        Key::Score => "{bulls}, {cows}.",
+        Key::Bull => "{count} Bulle",
+        Key::Bulls => "{count} Bullen",
+        Key::Cow => "{count} Kuh",
+        Key::Cows => "{count} Kühe",
+        Key::CodeLost => "Verloren! Der Code war {code}.",
+        Key::ReverseRules => "Denk dir eine Zahl zwischen {min} und {max} aus, und ich errate sie.",
+        Key::ReverseReplies => "Antworte auf jeden Tipp mit higher, lower oder yes.",
+        Key::ReversePrompt => "Ist es {guess}?",
+        Key::ReverseWin => "Erraten: {guess} nach {attempts} Tipps!",
+        Key::Contradiction => "Das kann nicht stimmen: Keine Zahl passt zu all deinen Antworten.",
+        Key::Joined => "Du bist Spieler {id}. Warte auf die anderen Spieler...",
+        Key::RaceLost => "Spieler {winner} war schneller. Die Zahl war {secret}.",
+        Key::OutOfAttempts => "Niemand hat sie rechtzeitig erraten. Die Zahl war {secret}.",
+    }
+}
+
+/*
+    The language of the game's text. Each locale has a catalog that maps every Key to a template,
+    and templates name their arguments in braces, like "{min}", so a translation is free to put
+    them in a different order. A catalog is one exhaustive match, so a locale that is missing a
+    key doesn't compile; the tests check that every translation takes the English arguments.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::German];
+
+    /// The language part of a locale name such as `de_DE.UTF-8`, or `None` if it isn't supported.
+    pub fn from_lang(lang: &str) -> Option<Locale> {
+        let language = lang.split(['_', '.', '@', '-']).next().unwrap_or("");
+        language.parse().ok()
+    }
+
+    /// The locale named by the `LANG` environment variable, or English.
+    pub fn from_env() -> Locale {
+        env::var("LANG")
+            .ok()
+            .and_then(|lang| Locale::from_lang(&lang))
+            .unwrap_or_default()
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::German => "de",
+        }
+    }
+
+    /// The template for `key`, with its `{placeholders}` left in.
+    pub fn template(self, key: Key) -> &'static str {
+        match self {
+            Locale::English => english(key),
+            Locale::German => german(key),
+        }
+    }
+
+    /// The message for `key` with each `{name}` replaced by its value from `arguments`.
+    pub fn format(self, key: Key, arguments: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut message = String::from(self.template(key));
+        for (name, value) in arguments {
+            message = message.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        message
+    }
+
+    pub fn message(self, key: Key) -> String {
+        self.format(key, &[])
+    }
+
+    /// Describes `error` in this locale.
+    pub fn guess_error(self, error: &GuessError) -> String {
+        match error {
+            GuessError::Parse { input, .. } if input.is_empty() => self.message(Key::EmptyInput),
+            GuessError::Parse { input, .. } => self.format(Key::NotANumber, &[("input", input)]),
+            GuessError::OutOfRange { value, min, max } => {
+                self.format(Key::OutOfRange, &[("min", min), ("max", max), ("value", value)])
+            }
+            GuessError::GameOver => self.message(Key::GameOver),
+            GuessError::Code { input, length } => {
+                self.format(Key::NotACode, &[("input", input), ("length", length)])
+            }
+            GuessError::Reply { .. } => self.message(Key::NotAReply),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLocaleError(String);
+
+impl fmt::Display for ParseLocaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported locale `{}` (expected en or de)", self.0)
+    }
+}
+
+impl Error for ParseLocaleError {}
+
+impl FromStr for Locale {
+    type Err = ParseLocaleError;
+
+    fn from_str(s: &str) -> Result<Locale, ParseLocaleError> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" => Ok(Locale::English),
+            "de" => Ok(Locale::German),
+            _ => Err(ParseLocaleError(String::from(s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn translations_take_the_english_arguments() {
+        for locale in Locale::ALL {
+            for &key in Key::ALL {
+                let template = locale.template(key);
+                assert!(!template.is_empty(), "{locale} {key:?} is empty");
+                assert_eq!(placeholders(template), placeholders(english(key)), "{locale} {key:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn picks_the_locale_from_lang() {
+        assert_eq!(Locale::from_lang("de_DE.UTF-8"), Some(Locale::German));
+        assert_eq!(Locale::from_lang("en_GB"), Some(Locale::English));
+        assert_eq!(Locale::from_lang("C"), None);
+        assert_eq!(Locale::from_lang(""), None);
+        assert_eq!("DE".parse(), Ok(Locale::German));
+    }
+
+    #[test]
+    fn formats_named_arguments() {
+        assert_eq!(
+            Locale::German.format(Key::OutOfRange, &[("value", &0), ("min", &1), ("max", &100)]),
+            "Der Tipp muss zwischen 1 und 100 liegen, nicht 0."
+        );
+        let error = GuessError::OutOfRange { value: 0, min: 1, max: 100 };
+        assert_eq!(Locale::English.guess_error(&error), error.to_string());
+    }
+}
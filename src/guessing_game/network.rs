@@ -0,0 +1,319 @@
+use super::{Feedback, Game, Key, Locale, Outcome, Strategy};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/*
+    A race over TCP: every player guesses the same secret number at their own pace, and the first
+    correct guess wins. If the game's settings limit the attempts, the limit is shared: once the
+    players together have used it up without guessing the number, everybody loses. The protocol
+    is one line of text per message, so it can even be played with telnet or nc.
+
+    Server to client:
+        WELCOME <player id> <min> <max>    sent when the client connects
+        START                              everybody has joined; start guessing
+        TOO_SMALL <guess> / TOO_BIG <guess>
+        INVALID <reason>                   the line was not a valid guess, in the game's locale
+        WIN <secret>                       you guessed it
+        OVER <winner id> <secret>          somebody else guessed it
+        LOST <secret>                      the attempts ran out; nobody guessed it
+
+    Client to server: one guess per line, for example "42".
+
+    After WIN/OVER/LOST the server closes its side of every connection. It keeps reading (and
+    ignoring) guesses that were already on their way until each client hangs up, so no client
+    loses its final message to a connection reset.
+ */
+
+struct Race {
+    game: Game,
+    winner: Option<usize>,
+    attempts: Vec<u32>,
+    players: Vec<TcpStream>,
+}
+
+/// What the server saw at the end of a race.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaceResult {
+    /// `None` if the attempts ran out, or every player left, before anyone guessed the number.
+    pub winner: Option<usize>,
+    pub secret_number: Option<i32>,
+    /// Valid guesses per player id.
+    pub attempts: Vec<u32>,
+}
+
+pub struct Server {
+    listener: TcpListener,
+    game: Game,
+}
+
+impl Server {
+    /// Listens on `address` (use port 0 to let the OS pick one) for a race over `game`'s range.
+    pub fn bind<A: ToSocketAddrs>(address: A, game: Game) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            game,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Waits for `players` clients, runs the race and returns once every client has left.
+    pub fn run(self, players: usize) -> io::Result<RaceResult> {
+        let (min, max) = (*self.game.range().start(), *self.game.range().end());
+        let mut streams = vec![];
+
+        for id in 0..players {
+            let (mut stream, _) = self.listener.accept()?;
+            writeln!(stream, "WELCOME {id} {min} {max}")?;
+            streams.push(stream);
+        }
+
+        let race = Arc::new(Mutex::new(Race {
+            game: self.game,
+            winner: None,
+            attempts: vec![0; players],
+            players: streams.iter().map(TcpStream::try_clone).collect::<io::Result<_>>()?,
+        }));
+
+        // Everybody hears START before anybody's guesses are answered.
+        for stream in &mut streams {
+            writeln!(stream, "START")?;
+        }
+
+        let mut handles = vec![];
+        for (id, stream) in streams.into_iter().enumerate() {
+            let race = Arc::clone(&race);
+            handles.push(thread::spawn(move || {
+                // A player whose connection fails simply drops out of the race.
+                let _ = handle_player(id, stream, &race);
+            }));
+        }
+
+        for handle in handles {
+            handle.join().expect("player thread panicked");
+        }
+
+        let race = race.lock().unwrap();
+        Ok(RaceResult {
+            winner: race.winner,
+            secret_number: race.game.reveal(),
+            attempts: race.attempts.clone(),
+        })
+    }
+}
+
+fn handle_player(id: usize, mut stream: TcpStream, race: &Mutex<Race>) -> io::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+
+    for line in reader.lines() {
+        let line = line?;
+        let mut race = race.lock().unwrap();
+        if race.game.outcome().is_some() {
+            continue;
+        }
+
+        let guess = race.game.validate(&line).map(|guess| guess.value());
+        let (guess, feedback) = match guess.and_then(|guess| Ok((guess, race.game.guess(guess)?))) {
+            Ok(played) => played,
+            Err(error) => {
+                writeln!(stream, "INVALID {}", race.game.settings().locale().guess_error(&error))?;
+                continue;
+            }
+        };
+        race.attempts[id] += 1;
+
+        match feedback {
+            Feedback::TooSmall => writeln!(stream, "TOO_SMALL {guess}")?,
+            Feedback::TooBig => writeln!(stream, "TOO_BIG {guess}")?,
+            Feedback::Correct => race.winner = Some(id),
+        }
+        if let Some(outcome) = race.game.outcome() {
+            finish(&mut race, outcome, id, guess);
+        }
+    }
+    Ok(())
+}
+
+// Tells every player how the race ended and closes the server's side of their connections.
+fn finish(race: &mut Race, outcome: Outcome, last_player: usize, guess: i32) {
+    for (other, player) in race.players.iter_mut().enumerate() {
+        let _ = match outcome {
+            Outcome::Won if other == last_player => writeln!(player, "WIN {guess}"),
+            Outcome::Won => writeln!(player, "OVER {last_player} {guess}"),
+            Outcome::Lost => writeln!(player, "LOST {}", race.game.secret_number),
+        };
+        let _ = player.shutdown(Shutdown::Write);
+    }
+}
+
+/// How a race ended for one client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceOutcome {
+    Won { secret_number: i32 },
+    Lost { winner: usize, secret_number: i32 },
+    /// The attempts ran out before anybody guessed the number.
+    OutOfAttempts { secret_number: i32 },
+}
+
+/*
+    The client side of the protocol. Where the guesses come from is up to the caller: join reads
+    them from a Strategy, join_interactive from a person typing at a terminal.
+ */
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    id: usize,
+    min: i32,
+    max: i32,
+}
+
+enum Message {
+    Start,
+    TooSmall(i32),
+    TooBig(i32),
+    Invalid(String),
+    Win(i32),
+    Over(usize, i32),
+    Lost(i32),
+}
+
+impl Client {
+    fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Client> {
+        let writer = TcpStream::connect(address)?;
+        let mut client = Client {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+            id: 0,
+            min: 0,
+            max: 0,
+        };
+
+        let welcome = client.read_line()?;
+        let fields: Vec<&str> = welcome.split_whitespace().collect();
+        match fields.as_slice() {
+            ["WELCOME", id, min, max] => {
+                client.id = id.parse().map_err(|_| invalid(&welcome))?;
+                client.min = min.parse().map_err(|_| invalid(&welcome))?;
+                client.max = max.parse().map_err(|_| invalid(&welcome))?;
+                Ok(client)
+            }
+            _ => Err(invalid(&welcome)),
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the server hung up"));
+        }
+        Ok(String::from(line.trim_end()))
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        let line = self.read_line()?;
+        let (kind, rest) = line.split_once(' ').unwrap_or((&line, ""));
+        let number = |text: &str| text.parse::<i32>().map_err(|_| invalid(&line));
+
+        match kind {
+            "START" => Ok(Message::Start),
+            "TOO_SMALL" => Ok(Message::TooSmall(number(rest)?)),
+            "TOO_BIG" => Ok(Message::TooBig(number(rest)?)),
+            "INVALID" => Ok(Message::Invalid(String::from(rest))),
+            "WIN" => Ok(Message::Win(number(rest)?)),
+            "OVER" => {
+                let (winner, secret) = rest.split_once(' ').ok_or_else(|| invalid(&line))?;
+                let winner = winner.parse().map_err(|_| invalid(&line))?;
+                Ok(Message::Over(winner, number(secret)?))
+            }
+            "LOST" => Ok(Message::Lost(number(rest)?)),
+            _ => Err(invalid(&line)),
+        }
+    }
+
+    fn send(&mut self, guess: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", guess.trim())
+    }
+}
+
+fn invalid(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("unexpected message from server: {line}"))
+}
+
+/// Joins the race at `address` and lets `strategy` do the guessing.
+pub fn join<A: ToSocketAddrs>(address: A, strategy: &mut dyn Strategy) -> io::Result<RaceOutcome> {
+    let mut client = Client::connect(address)?;
+    strategy.start(&(client.min..=client.max));
+
+    loop {
+        match client.receive()? {
+            Message::Start | Message::Invalid(_) => {}
+            Message::TooSmall(guess) => strategy.learn(guess, Feedback::TooSmall),
+            Message::TooBig(guess) => strategy.learn(guess, Feedback::TooBig),
+            Message::Win(secret_number) => return Ok(RaceOutcome::Won { secret_number }),
+            Message::Over(winner, secret_number) => return Ok(RaceOutcome::Lost { winner, secret_number }),
+            Message::Lost(secret_number) => return Ok(RaceOutcome::OutOfAttempts { secret_number }),
+        }
+        let guess = strategy.next_guess();
+        client.send(&guess.to_string())?;
+    }
+}
+
+/// Joins the race at `address`, reading guesses from `input` and showing the answers on `output`
+/// in `locale`. Why a guess was invalid comes from the server, in the locale of its game.
+pub fn join_interactive<A, R, W>(address: A, locale: Locale, mut input: R, mut output: W) -> anyhow::Result<RaceOutcome>
+    where
+        A: ToSocketAddrs,
+        R: BufRead,
+        W: Write,
+{
+    let mut client = Client::connect(address)?;
+    writeln!(output, "{}", locale.format(Key::Joined, &[("id", &client.id)]))?;
+
+    loop {
+        match client.receive()? {
+            Message::Start => {
+                writeln!(output, "{}", locale.message(Key::Title))?;
+                writeln!(output, "{}", locale.format(Key::Range, &[("min", &client.min), ("max", &client.max)]))?;
+            }
+            Message::TooSmall(_) => writeln!(output, "{}", locale.message(Key::TooSmall))?,
+            Message::TooBig(_) => writeln!(output, "{}", locale.message(Key::TooBig))?,
+            Message::Invalid(reason) => writeln!(output, "{reason}")?,
+            Message::Win(secret_number) => {
+                writeln!(output, "{}", locale.message(Key::Win))?;
+                return Ok(RaceOutcome::Won { secret_number });
+            }
+            Message::Over(winner, secret_number) => {
+                let message = locale.format(Key::RaceLost, &[("winner", &winner), ("secret", &secret_number)]);
+                writeln!(output, "{message}")?;
+                return Ok(RaceOutcome::Lost { winner, secret_number });
+            }
+            Message::Lost(secret_number) => {
+                writeln!(output, "{}", locale.format(Key::OutOfAttempts, &[("secret", &secret_number)]))?;
+                return Ok(RaceOutcome::OutOfAttempts { secret_number });
+            }
+        }
+
+        writeln!(output, "{}", locale.message(Key::Prompt))?;
+        let mut guess = String::new();
+        if input.read_line(&mut guess)? == 0 {
+            anyhow::bail!("the input ended before the game was over");
+        }
+        client.send(&guess)?;
+    }
+}
+
+/// Hosts a race for `players` players on `address` with a randomly chosen number.
+pub fn host_game<A: ToSocketAddrs>(address: A, players: usize, game: Game) -> io::Result<RaceResult> {
+    let server = Server::bind(address, game)?;
+    println!("Waiting for {players} players on {}", server.local_addr()?);
+    server.run(players)
+}
+
+/// Joins a race from the terminal, in the language of `LANG`.
+pub fn join_game<A: ToSocketAddrs>(address: A) -> anyhow::Result<RaceOutcome> {
+    join_interactive(address, Locale::from_env(), io::stdin().lock(), io::stdout())
+}
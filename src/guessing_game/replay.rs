@@ -0,0 +1,531 @@
+use super::{play, terminal_settings, Game, Locale, Outcome, Settings};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const HEADER: &str = "# guessing game replay v2";
+// Version 1 had no locale line; its games were all in English.
+const HEADER_V1: &str = "# guessing game replay v1";
+
+/// Something that happened during a session, in the order it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A line the player typed, including its newline, and when they typed it.
+    Input { at: Duration, line: String },
+    /// Text the game wrote between two inputs.
+    Output(String),
+}
+
+/*
+    A replay is everything needed to play a session again: the seed of the random number
+    generator (which decides the secret number), the settings, and the session itself.
+
+    The generator is ChaCha12, named explicitly instead of taken as rand's StdRng: StdRng may
+    switch algorithms in any rand release, and then every saved replay would draw a different
+    secret. ChaCha12 is what StdRng was when versions 1 and 2 were written, so their files still
+    play back.
+
+    The file is line-based text, so a replay can be attached to a bug report and read by a person:
+
+        # guessing game replay v2
+        seed 42
+        range 1 100
+        attempts none
+        locale en
+        out Guess the number!\nThe number is between 1 and 100.\nPlease input your guess.\n
+        in 2150 50\n
+        out Too big. Try a small number!\nPlease input your guess.\n
+
+    Version 1 files, written before games had a locale, have no `locale` line and are read as
+    English. `in` lines carry the milliseconds since the start of the game. Backslashes, newlines,
+    carriage returns and tabs in the text are escaped as \\, \n, \r and \t.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: Settings,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// The replay file is malformed at `line` (counting from 1).
+    Format { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "I/O error: {error}"),
+            ReplayError::Format { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReplayError::Io(error) => Some(error),
+            ReplayError::Format { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> ReplayError {
+        ReplayError::Io(error)
+    }
+}
+
+/// Where a playback first differed from the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index into `Replay::events`.
+    pub event: usize,
+    pub expected: Option<Event>,
+    pub actual: Option<Event>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "event {} differs: expected {:?}, got {:?}",
+            self.event, self.expected, self.actual
+        )
+    }
+}
+
+impl Error for Mismatch {}
+
+/*
+    The tape is shared by the input and output wrappers below through Rc<RefCell<..>>, because
+    both are handed to play by value but have to append to the same list of events in order.
+
+    Output arrives in whatever pieces the game writes, and a piece may end in the middle of a
+    multi-byte character, so the bytes are collected as they are and only decoded once the output
+    is complete: when the next input is read, or when the game ends.
+ */
+#[derive(Default)]
+struct Recording {
+    events: Vec<Event>,
+    output: Vec<u8>,
+}
+
+impl Recording {
+    fn finish_output(&mut self) {
+        if !self.output.is_empty() {
+            let text = String::from_utf8_lossy(&self.output).into_owned();
+            self.events.push(Event::Output(text));
+            self.output.clear();
+        }
+    }
+}
+
+type Tape = Rc<RefCell<Recording>>;
+
+struct TapeInput<R> {
+    inner: R,
+    tape: Tape,
+    started: Instant,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead> TapeInput<R> {
+    fn finish_line(&mut self) {
+        if !self.line.is_empty() {
+            let line = String::from_utf8_lossy(&self.line).into_owned();
+            // Replay files keep milliseconds, so keep the same precision in memory.
+            let at = Duration::from_millis(self.started.elapsed().as_millis() as u64);
+            let mut tape = self.tape.borrow_mut();
+            tape.finish_output();
+            tape.events.push(Event::Input { at, line });
+            self.line.clear();
+        }
+    }
+}
+
+impl<R: BufRead> Read for TapeInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+// Records every byte the game consumes, one Input event per line.
+impl<R: BufRead> BufRead for TapeInput<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.inner.fill_buf()?.is_empty() {
+            self.finish_line();
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Ok(available) = self.inner.fill_buf() {
+            let consumed = &available[..amount.min(available.len())];
+            self.line.extend_from_slice(consumed);
+        }
+        self.inner.consume(amount);
+        if self.line.ends_with(b"\n") {
+            self.finish_line();
+        }
+    }
+}
+
+struct TapeOutput<W> {
+    inner: W,
+    tape: Tape,
+}
+
+impl<W: Write> Write for TapeOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.tape.borrow_mut().output.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Plays a classic game seeded with `seed` and returns everything that happened.
+fn run_taped<R: BufRead, W: Write>(
+    seed: u64,
+    settings: &Settings,
+    input: R,
+    output: W,
+) -> (anyhow::Result<Outcome>, Vec<Event>) {
+    let tape: Tape = Rc::new(RefCell::new(Recording::default()));
+    let mut game = Game::with_settings(&mut ChaCha12Rng::seed_from_u64(seed), settings.clone());
+
+    let input = TapeInput {
+        inner: input,
+        tape: Rc::clone(&tape),
+        started: Instant::now(),
+        line: vec![],
+    };
+    let output = TapeOutput { inner: output, tape: Rc::clone(&tape) };
+    let result = play(&mut game, input, output);
+
+    let mut tape = tape.borrow_mut();
+    tape.finish_output();
+    (result, tape.events.clone())
+}
+
+/// Plays a game seeded with `seed` like `play` does, and also returns a recording of it.
+pub fn record<R: BufRead, W: Write>(
+    seed: u64,
+    settings: Settings,
+    input: R,
+    output: W,
+) -> (anyhow::Result<Outcome>, Replay) {
+    let (result, events) = run_taped(seed, &settings, input, output);
+    (result, Replay { seed, settings, events })
+}
+
+impl Replay {
+    /// Plays the recorded input into a fresh game and checks every output matches byte for byte.
+    pub fn verify(&self) -> Result<(), Mismatch> {
+        let input: String = self
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Input { line, .. } => Some(line.as_str()),
+                Event::Output(_) => None,
+            })
+            .collect();
+        let (_, actual) = run_taped(self.seed, &self.settings, input.as_bytes(), io::sink());
+
+        // Timings naturally differ, so only the text of the inputs is compared.
+        let same = |expected: &Event, actual: &Event| match (expected, actual) {
+            (Event::Input { line: a, .. }, Event::Input { line: b, .. }) => a == b,
+            (a, b) => a == b,
+        };
+
+        for index in 0..self.events.len().max(actual.len()) {
+            let (expected, actual) = (self.events.get(index), actual.get(index));
+            let matches = match (expected, actual) {
+                (Some(expected), Some(actual)) => same(expected, actual),
+                _ => false,
+            };
+            if !matches {
+                return Err(Mismatch {
+                    event: index,
+                    expected: expected.cloned(),
+                    actual: actual.cloned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, mut output: W) -> io::Result<()> {
+        writeln!(output, "{HEADER}")?;
+        writeln!(output, "seed {}", self.seed)?;
+        writeln!(output, "range {} {}", self.settings.range.start(), self.settings.range.end())?;
+        match self.settings.max_attempts {
+            Some(max_attempts) => writeln!(output, "attempts {max_attempts}")?,
+            None => writeln!(output, "attempts none")?,
+        }
+        writeln!(output, "locale {}", self.settings.locale())?;
+        for event in &self.events {
+            match event {
+                Event::Input { at, line } => writeln!(output, "in {} {}", at.as_millis(), escape(line))?,
+                Event::Output(text) => writeln!(output, "out {}", escape(text))?,
+            }
+        }
+        output.flush()
+    }
+
+    pub fn read_from<R: BufRead>(input: R) -> Result<Replay, ReplayError> {
+        let mut lines = input.lines().enumerate().map(|(index, line)| (index + 1, line));
+        let mut read = 0;
+        // A missing line is reported at the line after the last one read.
+        let mut next = |expected: &str| -> Result<(usize, String), ReplayError> {
+            match lines.next() {
+                Some((number, line)) => {
+                    read = number;
+                    Ok((number, line?))
+                }
+                None => Err(ReplayError::Format { line: read + 1, message: format!("missing {expected}") }),
+            }
+        };
+        let format_error = |line: usize, message: &str| ReplayError::Format { line, message: String::from(message) };
+
+        let (number, header) = next("header")?;
+        let has_locale = match header.as_str() {
+            HEADER => true,
+            HEADER_V1 => false,
+            _ => return Err(format_error(number, "not a guessing game replay")),
+        };
+
+        let (number, seed) = next("seed")?;
+        let seed = seed
+            .strip_prefix("seed ")
+            .and_then(|seed| seed.parse().ok())
+            .ok_or_else(|| format_error(number, "expected `seed <number>`"))?;
+
+        let (number, range) = next("range")?;
+        let range = range
+            .strip_prefix("range ")
+            .and_then(|range| range.split_once(' '))
+            .and_then(|(min, max)| Some(min.parse::<i32>().ok()?..=max.parse::<i32>().ok()?))
+            .filter(|range| !range.is_empty())
+            .ok_or_else(|| format_error(number, "expected `range <min> <max>`"))?;
+
+        let (number, attempts) = next("attempts")?;
+        let max_attempts = match attempts.strip_prefix("attempts ") {
+            Some("none") => None,
+            Some(max) => Some(max.parse().map_err(|_| format_error(number, "expected `attempts <number>`"))?),
+            None => return Err(format_error(number, "expected `attempts <number|none>`")),
+        };
+
+        let locale = if has_locale {
+            let (number, locale) = next("locale")?;
+            locale
+                .strip_prefix("locale ")
+                .and_then(|locale| locale.parse().ok())
+                .ok_or_else(|| format_error(number, "expected `locale <en|de>`"))?
+        } else {
+            Locale::English
+        };
+
+        let mut events = vec![];
+        for (number, line) in lines {
+            let line = line?;
+            if let Some(text) = line.strip_prefix("out ") {
+                events.push(Event::Output(unescape(text).ok_or_else(|| format_error(number, "bad escape"))?));
+            } else if let Some(rest) = line.strip_prefix("in ") {
+                let (millis, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                let millis = millis.parse().map_err(|_| format_error(number, "expected `in <ms> <text>`"))?;
+                let line = unescape(text).ok_or_else(|| format_error(number, "bad escape"))?;
+                events.push(Event::Input { at: Duration::from_millis(millis), line });
+            } else if !line.is_empty() {
+                return Err(format_error(number, "expected an `in` or `out` event"));
+            }
+        }
+
+        Ok(Replay {
+            seed,
+            settings: Settings::new(range, max_attempts).with_locale(locale),
+            events,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Plays one game on the terminal and saves a replay of it to `path`, even if the game failed.
+pub fn start_recorded_game<P: AsRef<Path>>(settings: impl Into<Settings>, path: P) -> anyhow::Result<()> {
+    let (result, replay) = record(rand::random(), terminal_settings(settings), io::stdin().lock(), io::stdout());
+    replay.save(path)?;
+    result?;
+    Ok(())
+}
+
+/// Loads the replay at `path`, plays it back and reports whether the game still behaves the same.
+pub fn play_back<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+    let replay = Replay::load(path)?;
+    replay.verify()?;
+    println!("The replay of seed {} matches.", replay.seed);
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            't' => unescaped.push('\t'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guessing_game::Difficulty;
+
+    #[test]
+    fn a_recorded_session_plays_back_identically() {
+        let input = "50\nnope\n25\n75\n12\n88\n";
+        let (_, replay) = record(11, Difficulty::Easy.settings(), input.as_bytes(), io::sink());
+
+        assert_eq!(replay.verify(), Ok(()));
+        let inputs: Vec<&str> = replay
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Input { line, .. } => Some(line.as_str()),
+                Event::Output(_) => None,
+            })
+            .collect();
+        assert!(inputs.len() <= 6);
+        assert_eq!(inputs[..2], ["50\n", "nope\n"]);
+        assert!(matches!(&replay.events[0], Event::Output(text) if text.starts_with("Guess the number!\n")));
+    }
+
+    #[test]
+    fn round_trips_through_the_file_format() {
+        let (_, replay) = record(3, Settings::default().with_locale(Locale::English), "1\n\tx\\y\n100\n".as_bytes(), io::sink());
+
+        let mut file = vec![];
+        replay.write_to(&mut file).unwrap();
+        let text = String::from_utf8(file.clone()).unwrap();
+        assert!(text.starts_with("# guessing game replay v2\nseed 3\nrange 1 100\nattempts none\nlocale en\nout Guess the number!\\n"));
+        assert!(text.contains(" \\tx\\\\y\\n\n"));
+
+        let loaded = Replay::read_from(file.as_slice()).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.verify(), Ok(()));
+    }
+
+    // Replays on disk depend on these numbers, so they must survive any dependency update.
+    #[test]
+    fn a_seed_always_draws_the_same_secret() {
+        let secrets: Vec<i32> = [0, 1, 42]
+            .iter()
+            .map(|&seed| Game::with_settings(&mut ChaCha12Rng::seed_from_u64(seed), Settings::default()).secret_number)
+            .collect();
+        assert_eq!(secrets, vec![81, 83, 14]);
+    }
+
+    #[test]
+    fn playback_detects_a_different_game() {
+        let (_, mut replay) = record(5, Settings::default(), "50\n".as_bytes(), io::sink());
+        assert_eq!(replay.verify(), Ok(()));
+
+        // With another seed the secret number differs, so the answers to the same input differ.
+        replay.seed = 8;
+        let mismatch = replay.verify().unwrap_err();
+        assert_eq!(mismatch.event, 2);
+
+        if let Some(Event::Output(text)) = replay.events.get_mut(0) {
+            text.push('!');
+        }
+        assert_eq!(replay.verify().unwrap_err().event, 0);
+    }
+
+    #[test]
+    fn reports_the_line_of_a_malformed_file() {
+        let file = "# guessing game replay v2\nseed 1\nrange 1 100\nattempts none\nlocale en\nout ok\nsideways\n";
+
+        match Replay::read_from(file.as_bytes()) {
+            Err(ReplayError::Format { line, .. }) => assert_eq!(line, 7),
+            other => panic!("expected a format error, got {other:?}"),
+        }
+        match Replay::read_from("# guessing game replay v2\nseed 1\n".as_bytes()) {
+            Err(ReplayError::Format { line, message }) => assert_eq!((line, message.as_str()), (3, "missing range")),
+            other => panic!("expected a format error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_version_1_files_as_english() {
+        let (_, replay) = record(3, Settings::default().with_locale(Locale::English), "50\n".as_bytes(), io::sink());
+        let mut file = vec![];
+        replay.write_to(&mut file).unwrap();
+        let text = String::from_utf8(file).unwrap();
+        let v1 = text.replace(HEADER, HEADER_V1).replace("locale en\n", "");
+
+        let loaded = Replay::read_from(v1.as_bytes()).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.verify(), Ok(()));
+        assert!(Replay::read_from(text.replace("locale en\n", "").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn characters_split_across_writes_are_recorded_whole() {
+        let tape: Tape = Rc::new(RefCell::new(Recording::default()));
+        let mut output = TapeOutput { inner: io::sink(), tape: Rc::clone(&tape) };
+        let text = "Zu groß.".as_bytes();
+        let split = text.len() - 2; // between the two bytes of ß
+        output.write_all(&text[..split]).unwrap();
+        output.write_all(&text[split..]).unwrap();
+
+        tape.borrow_mut().finish_output();
+        assert_eq!(tape.borrow().events, vec![Event::Output(String::from("Zu groß."))]);
+    }
+}
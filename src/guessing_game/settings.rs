@@ -0,0 +1,103 @@
+use super::Locale;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// The range of the classic game, and of `Guess::new`.
+pub const DEFAULT_RANGE: RangeInclusive<i32> = 1..=100;
+
+/*
+    Everything that can be tuned about a game. The range lives here only once: the Game draws its
+    secret number from it and validates guesses against it, so the two can't drift apart.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub range: RangeInclusive<i32>,
+    /// `None` lets the player guess until they win.
+    pub max_attempts: Option<u32>,
+    /// `None` leaves the choice to whoever starts the game; the terminal games follow `LANG`.
+    pub locale: Option<Locale>,
+}
+
+impl Settings {
+    pub fn new(range: RangeInclusive<i32>, max_attempts: Option<u32>) -> Settings {
+        Settings { range, max_attempts, locale: None }
+    }
+
+    pub fn with_locale(self, locale: Locale) -> Settings {
+        Settings { locale: Some(locale), ..self }
+    }
+
+    /// The language of the game's text, English unless a locale was chosen.
+    pub fn locale(&self) -> Locale {
+        self.locale.unwrap_or_default()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new(DEFAULT_RANGE, None)
+    }
+}
+
+/// Presets for the range and the number of attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    /// 1 to 50 with 10 attempts: binary search needs at most 6.
+    Easy,
+    /// 1 to 100 with 8 attempts: binary search needs at most 7.
+    Normal,
+    /// 1 to 1000 with 10 attempts: only a perfect binary search is sure to win.
+    Hard,
+}
+
+impl Difficulty {
+    pub fn settings(self) -> Settings {
+        match self {
+            Difficulty::Easy => Settings::new(1..=50, Some(10)),
+            Difficulty::Normal => Settings::new(1..=100, Some(8)),
+            Difficulty::Hard => Settings::new(1..=1000, Some(10)),
+        }
+    }
+}
+
+impl From<Difficulty> for Settings {
+    fn from(difficulty: Difficulty) -> Settings {
+        difficulty.settings()
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDifficultyError(String);
+
+impl fmt::Display for ParseDifficultyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown difficulty `{}` (expected easy, normal or hard)", self.0)
+    }
+}
+
+impl Error for ParseDifficultyError {}
+
+impl FromStr for Difficulty {
+    type Err = ParseDifficultyError;
+
+    fn from_str(name: &str) -> Result<Difficulty, ParseDifficultyError> {
+        match name.trim().to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(ParseDifficultyError(String::from(name))),
+        }
+    }
+}
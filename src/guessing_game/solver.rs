@@ -0,0 +1,260 @@
+use super::{Feedback, Game, GuessError, Outcome, Settings};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/*
+    A Strategy plays the guessing game on its own: it is told the range when a game starts,
+    proposes guesses, and learns from the feedback to each one. Strategies only see what a human
+    player would see, never the secret number, so they can be used to benchmark the engine or to
+    compare approaches in class.
+ */
+pub trait Strategy {
+    fn name(&self) -> &str;
+
+    /// Forgets the previous game and prepares for a new one over `range`.
+    fn start(&mut self, range: &RangeInclusive<i32>);
+
+    fn next_guess(&mut self) -> i32;
+
+    fn learn(&mut self, guess: i32, feedback: Feedback);
+}
+
+/// Always guesses the middle of the numbers that are still possible.
+#[derive(Default)]
+pub struct BinarySearch {
+    low: i32,
+    high: i32,
+}
+
+impl BinarySearch {
+    pub fn new() -> BinarySearch {
+        BinarySearch::default()
+    }
+}
+
+impl Strategy for BinarySearch {
+    fn name(&self) -> &str {
+        "binary search"
+    }
+
+    fn start(&mut self, range: &RangeInclusive<i32>) {
+        (self.low, self.high) = (*range.start(), *range.end());
+    }
+
+    fn next_guess(&mut self) -> i32 {
+        self.low + (self.high - self.low) / 2
+    }
+
+    fn learn(&mut self, guess: i32, feedback: Feedback) {
+        match feedback {
+            Feedback::TooSmall => self.low = guess + 1,
+            Feedback::TooBig => self.high = guess - 1,
+            Feedback::Correct => {}
+        }
+    }
+}
+
+/// Guesses a random number among the ones that are still possible.
+pub struct RandomGuess<R: Rng> {
+    rng: R,
+    low: i32,
+    high: i32,
+}
+
+impl<R: Rng> RandomGuess<R> {
+    pub fn new(rng: R) -> RandomGuess<R> {
+        RandomGuess { rng, low: 0, high: 0 }
+    }
+}
+
+impl<R: Rng> Strategy for RandomGuess<R> {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn start(&mut self, range: &RangeInclusive<i32>) {
+        (self.low, self.high) = (*range.start(), *range.end());
+    }
+
+    fn next_guess(&mut self) -> i32 {
+        self.rng.gen_range(self.low..=self.high)
+    }
+
+    fn learn(&mut self, guess: i32, feedback: Feedback) {
+        match feedback {
+            Feedback::TooSmall => self.low = guess + 1,
+            Feedback::TooBig => self.high = guess - 1,
+            Feedback::Correct => {}
+        }
+    }
+}
+
+/// Counts up from the bottom of the range, ignoring everything but a win.
+#[derive(Default)]
+pub struct Linear {
+    next: i32,
+}
+
+impl Linear {
+    pub fn new() -> Linear {
+        Linear::default()
+    }
+}
+
+impl Strategy for Linear {
+    fn name(&self) -> &str {
+        "linear"
+    }
+
+    fn start(&mut self, range: &RangeInclusive<i32>) {
+        self.next = *range.start();
+    }
+
+    fn next_guess(&mut self) -> i32 {
+        self.next
+    }
+
+    fn learn(&mut self, guess: i32, _feedback: Feedback) {
+        self.next = guess + 1;
+    }
+}
+
+/// Lets `strategy` play `game` until it is won or lost, or until it guesses outside the range.
+pub fn solve(game: &mut Game, strategy: &mut dyn Strategy) -> Result<Outcome, GuessError> {
+    strategy.start(game.range());
+
+    loop {
+        let guess = strategy.next_guess();
+        let feedback = game.guess(guess)?;
+        strategy.learn(guess, feedback);
+
+        if let Some(outcome) = game.outcome() {
+            return Ok(outcome);
+        }
+    }
+}
+
+/*
+    Binary search halves the candidates with every wrong guess, so n candidates take at most
+    floor(log2(n)) + 1 guesses, which is ceil(log2(n + 1)): the number of bits needed to write n.
+    That equals ceil(log2(n)) unless n is a power of two, where one more guess can be needed
+    (1..=64 can take 7 guesses, not 6).
+ */
+/// The most guesses binary search can need for `range`.
+pub fn binary_search_bound(range: &RangeInclusive<i32>) -> u32 {
+    let candidates = (*range.end() as i64 - *range.start() as i64 + 1).max(0) as u64;
+    u64::BITS - candidates.leading_zeros()
+}
+
+/// The results of many games played by one strategy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Benchmark {
+    pub strategy: String,
+    pub games: usize,
+    pub wins: usize,
+    pub mean_attempts: f64,
+    pub worst_attempts: u32,
+    /// How many games took each number of attempts.
+    pub distribution: BTreeMap<u32, usize>,
+}
+
+/// Plays `games` games with `strategy`, drawing every secret number from one RNG seeded with `seed`.
+pub fn benchmark(strategy: &mut dyn Strategy, settings: &Settings, games: usize, seed: u64) -> Benchmark {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut wins = 0;
+    let mut distribution = BTreeMap::new();
+
+    for _ in 0..games {
+        let mut game = Game::with_settings(&mut rng, settings.clone());
+        if solve(&mut game, strategy) == Ok(Outcome::Won) {
+            wins += 1;
+        }
+        *distribution.entry(game.attempts()).or_insert(0) += 1;
+    }
+
+    let total: u64 = distribution
+        .iter()
+        .map(|(attempts, count)| *attempts as u64 * *count as u64)
+        .sum();
+
+    Benchmark {
+        strategy: String::from(strategy.name()),
+        games,
+        wins,
+        mean_attempts: if games == 0 { 0.0 } else { total as f64 / games as f64 },
+        worst_attempts: distribution.keys().next_back().copied().unwrap_or(0),
+        distribution,
+    }
+}
+
+impl fmt::Display for Benchmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} games, {} won, {:.2} attempts on average, {} at worst",
+            self.strategy, self.games, self.wins, self.mean_attempts, self.worst_attempts
+        )?;
+        for (attempts, count) in &self.distribution {
+            writeln!(f, "{attempts:>5} attempts: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guessing_game::Difficulty;
+
+    #[test]
+    fn binary_search_never_exceeds_its_bound() {
+        for range in [1..=100, 1..=1000, -50..=49] {
+            let settings = Settings::new(range.clone(), None);
+            let report = benchmark(&mut BinarySearch::new(), &settings, 5_000, 42);
+
+            assert_eq!(report.wins, 5_000);
+            assert!(report.worst_attempts <= binary_search_bound(&range), "{report}");
+        }
+        // ceil(log2(n)) for 100 and 1000 candidates, but one more for a power of two.
+        assert_eq!(binary_search_bound(&(1..=100)), 7);
+        assert_eq!(binary_search_bound(&(1..=1000)), 10);
+        assert_eq!(binary_search_bound(&(1..=64)), 7);
+        let report = benchmark(&mut BinarySearch::new(), &Settings::new(1..=64, None), 2_000, 42);
+        assert_eq!(report.worst_attempts, 7);
+    }
+
+    #[test]
+    fn benchmarks_are_reproducible_for_a_seed() {
+        let settings = Settings::default();
+        let first = benchmark(&mut RandomGuess::new(StdRng::seed_from_u64(1)), &settings, 2_000, 9);
+        let second = benchmark(&mut RandomGuess::new(StdRng::seed_from_u64(1)), &settings, 2_000, 9);
+
+        assert_eq!(first, second);
+        assert_eq!(first.distribution.values().sum::<usize>(), 2_000);
+    }
+
+    #[test]
+    fn strategies_rank_as_expected() {
+        let settings = Settings::default();
+        let binary = benchmark(&mut BinarySearch::new(), &settings, 3_000, 7);
+        let random = benchmark(&mut RandomGuess::new(StdRng::seed_from_u64(1)), &settings, 3_000, 7);
+        let linear = benchmark(&mut Linear::new(), &settings, 3_000, 7);
+
+        assert!(binary.mean_attempts < random.mean_attempts);
+        assert!(random.mean_attempts < linear.mean_attempts);
+        assert_eq!(linear.worst_attempts, 100);
+        // Linear guessing is 50.5 attempts on average for 1..=100.
+        assert!((linear.mean_attempts - 50.5).abs() < 2.0);
+    }
+
+    #[test]
+    fn attempt_limits_apply_to_strategies_too() {
+        let report = benchmark(&mut Linear::new(), &Difficulty::Normal.settings(), 1_000, 3);
+
+        assert!(report.wins < 1_000);
+        assert_eq!(report.worst_attempts, 8);
+    }
+}
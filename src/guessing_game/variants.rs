@@ -0,0 +1,593 @@
+use super::{Feedback, Game, GuessError, Key, Locale, Outcome, Settings, Strategy};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::io::{BufRead, Write};
+use std::ops::RangeInclusive;
+
+/*
+    Every variant is a conversation of the same shape: a few lines of introduction, then a prompt,
+    one line of input and an answer, over and over until the game has an outcome. The Variant
+    trait captures that shape, so play_variant is the one front-end for all of them, including
+    the classic game (play is play_variant with Classic).
+
+    The rules that are shared stay shared: numbers are validated against the range with
+    Guess::within and friends, invalid input comes back as a GuessError that is shown to the
+    player and doesn't cost an attempt, and every variant is scored the same way, by its
+    Outcome and the number of attempts it took.
+ */
+pub trait Variant {
+    /// Shown once before the first prompt.
+    fn introduction(&self) -> Vec<String>;
+
+    /// Shown before every line of input.
+    fn prompt(&self) -> String {
+        Locale::default().message(Key::Prompt)
+    }
+
+    /// Plays one turn and returns the answer to show, which may be empty or span several lines.
+    fn take_turn(&mut self, input: &str) -> Result<String, GuessError>;
+
+    /// How an invalid line is explained to the player.
+    fn describe_error(&self, error: &GuessError) -> String {
+        error.to_string()
+    }
+
+    /// `None` while the game is still going on.
+    fn outcome(&self) -> Option<Outcome>;
+
+    /// Valid turns taken so far.
+    fn attempts(&self) -> u32;
+}
+
+/// Plays `variant` interactively, reading one line per turn from `input` and writing to `output`.
+pub fn play_variant<V, R, W>(variant: &mut V, mut input: R, mut output: W) -> anyhow::Result<Outcome>
+    where
+        V: Variant + ?Sized,
+        R: BufRead,
+        W: Write,
+{
+    for line in variant.introduction() {
+        writeln!(output, "{line}")?;
+    }
+
+    loop {
+        writeln!(output, "{}", variant.prompt())?;
+
+        // Returns the number of bytes in the user’s input; 0 means the input has ended
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            anyhow::bail!("the input ended before the game was over");
+        }
+
+        // A bad line is reported and the player simply gets asked again
+        match variant.take_turn(&line) {
+            Ok(answer) if answer.is_empty() => {}
+            Ok(answer) => writeln!(output, "{answer}")?,
+            Err(error) => writeln!(output, "{}", variant.describe_error(&error))?,
+        }
+
+        if let Some(outcome) = variant.outcome() {
+            return Ok(outcome);
+        }
+    }
+}
+
+fn range_introduction(title: &str, settings: &Settings, locale: Locale) -> Vec<String> {
+    let (min, max) = (settings.range.start(), settings.range.end());
+    let mut lines = vec![
+        String::from(title),
+        locale.format(Key::Range, &[("min", min), ("max", max)]),
+    ];
+    if let Some(max_attempts) = settings.max_attempts {
+        lines.push(locale.format(Key::Attempts, &[("attempts", &max_attempts)]));
+    }
+    lines
+}
+
+/// The classic game: "Too small", "Too big" or "You win!", in the locale of the game's settings.
+pub struct Classic<'a> {
+    game: &'a mut Game,
+}
+
+impl<'a> Classic<'a> {
+    pub fn new(game: &'a mut Game) -> Classic<'a> {
+        Classic { game }
+    }
+}
+
+impl Variant for Classic<'_> {
+    fn introduction(&self) -> Vec<String> {
+        let locale = self.game.settings().locale();
+        range_introduction(&locale.message(Key::Title), self.game.settings(), locale)
+    }
+
+    fn prompt(&self) -> String {
+        self.game.settings().locale().message(Key::Prompt)
+    }
+
+    fn take_turn(&mut self, input: &str) -> Result<String, GuessError> {
+        let locale = self.game.settings().locale();
+        let guess = self.game.validate(input)?;
+
+        let mut answer = locale.message(match self.game.guess(guess.value())? {
+            Feedback::TooSmall => Key::TooSmall,
+            Feedback::TooBig => Key::TooBig,
+            Feedback::Correct => Key::Win,
+        });
+        if self.game.outcome() == Some(Outcome::Lost) {
+            answer.push('\n');
+            answer.push_str(&locale.format(Key::Lose, &[("secret", &self.game.secret_number)]));
+        }
+        Ok(answer)
+    }
+
+    fn describe_error(&self, error: &GuessError) -> String {
+        self.game.settings().locale().guess_error(error)
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        self.game.outcome()
+    }
+
+    fn attempts(&self) -> u32 {
+        self.game.attempts()
+    }
+}
+
+/// Says how close a guess is, but not in which direction.
+pub struct HotCold {
+    game: Game,
+}
+
+impl HotCold {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, settings: impl Into<Settings>) -> HotCold {
+        HotCold { game: Game::with_settings(rng, settings) }
+    }
+
+    /*
+        How hot a guess is depends on its distance to the secret as a share of the whole range,
+        so "Hot!" means the same thing in 1..=50 as in 1..=1000.
+     */
+    fn temperature(&self, guess: i32) -> Key {
+        let range = self.game.range();
+        let span = (*range.end() as f64 - *range.start() as f64 + 1.0).max(1.0);
+        let distance = (guess as f64 - self.game.secret_number as f64).abs() / span;
+
+        match distance {
+            d if d <= 0.02 => Key::Boiling,
+            d if d <= 0.05 => Key::Hot,
+            d if d <= 0.10 => Key::Warm,
+            d if d <= 0.25 => Key::Cool,
+            d if d <= 0.50 => Key::Cold,
+            _ => Key::Freezing,
+        }
+    }
+}
+
+impl Variant for HotCold {
+    fn introduction(&self) -> Vec<String> {
+        let locale = self.game.settings().locale();
+        let mut lines = range_introduction(&locale.message(Key::HotColdTitle), self.game.settings(), locale);
+        lines.push(locale.message(Key::HotColdRules));
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        self.game.settings().locale().message(Key::Prompt)
+    }
+
+    fn take_turn(&mut self, input: &str) -> Result<String, GuessError> {
+        let locale = self.game.settings().locale();
+        let guess = self.game.validate(input)?.value();
+
+        let mut answer = locale.message(match self.game.guess(guess)? {
+            Feedback::Correct => Key::Win,
+            _ => self.temperature(guess),
+        });
+        if self.game.outcome() == Some(Outcome::Lost) {
+            answer.push('\n');
+            answer.push_str(&locale.format(Key::Lose, &[("secret", &self.game.secret_number)]));
+        }
+        Ok(answer)
+    }
+
+    fn describe_error(&self, error: &GuessError) -> String {
+        self.game.settings().locale().guess_error(error)
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        self.game.outcome()
+    }
+
+    fn attempts(&self) -> u32 {
+        self.game.attempts()
+    }
+}
+
+/// Counts the digits of `guess` in the right place (bulls) and in the wrong place (cows).
+pub fn bulls_and_cows(secret: &[u8], guess: &[u8]) -> (usize, usize) {
+    let bulls = secret.iter().zip(guess).filter(|(s, g)| s == g).count();
+    let common = guess.iter().filter(|digit| secret.contains(digit)).count();
+    (bulls, common - bulls)
+}
+
+/// Mastermind with digits: guess a code of different digits from bulls and cows.
+pub struct BullsAndCows {
+    secret: Vec<u8>,
+    max_attempts: Option<u32>,
+    attempts: u32,
+    outcome: Option<Outcome>,
+    locale: Locale,
+}
+
+impl BullsAndCows {
+    /// # Panics
+    ///
+    /// Panics unless `length` is between 1 and 10, since the digits must all be different.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R, length: usize, max_attempts: Option<u32>) -> BullsAndCows {
+        assert!((1..=10).contains(&length), "a code has between 1 and 10 digits, not {length}");
+        let mut digits: Vec<u8> = (0..10).collect();
+        digits.shuffle(rng);
+        digits.truncate(length);
+
+        BullsAndCows {
+            secret: digits,
+            max_attempts,
+            attempts: 0,
+            outcome: None,
+            locale: Locale::default(),
+        }
+    }
+
+    /// Plays in `locale` instead of English.
+    pub fn with_locale(self, locale: Locale) -> BullsAndCows {
+        BullsAndCows { locale, ..self }
+    }
+
+    /// Validates a code: exactly as many digits as the secret, all of them different.
+    pub fn parse_code(&self, input: &str) -> Result<Vec<u8>, GuessError> {
+        let input = input.trim();
+        let error = || GuessError::Code { input: String::from(input), length: self.secret.len() };
+
+        let code: Vec<u8> = input
+            .chars()
+            .map(|c| c.to_digit(10).map(|digit| digit as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(error)?;
+        let all_different = code.iter().enumerate().all(|(i, digit)| !code[..i].contains(digit));
+
+        if code.len() != self.secret.len() || !all_different {
+            return Err(error());
+        }
+        Ok(code)
+    }
+
+    fn secret_text(&self) -> String {
+        self.secret.iter().map(|digit| digit.to_string()).collect()
+    }
+}
+
+impl Variant for BullsAndCows {
+    fn introduction(&self) -> Vec<String> {
+        let locale = self.locale;
+        let mut lines = vec![
+            locale.message(Key::BullsAndCowsTitle),
+            locale.format(Key::BullsAndCowsRules, &[("length", &self.secret.len())]),
+            locale.message(Key::BullsAndCowsHint),
+        ];
+        if let Some(max_attempts) = self.max_attempts {
+            lines.push(locale.format(Key::Attempts, &[("attempts", &max_attempts)]));
+        }
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        self.locale.message(Key::CodePrompt)
+    }
+
+    fn take_turn(&mut self, input: &str) -> Result<String, GuessError> {
+        if self.outcome.is_some() {
+            return Err(GuessError::GameOver);
+        }
+        let code = self.parse_code(input)?;
+        self.attempts += 1;
+
+        let (bulls, cows) = bulls_and_cows(&self.secret, &code);
+        let locale = self.locale;
+        if bulls == self.secret.len() {
+            self.outcome = Some(Outcome::Won);
+            return Ok(locale.message(Key::Win));
+        }
+
+        let count = |count: usize, one: Key, many: Key| {
+            locale.format(if count == 1 { one } else { many }, &[("count", &count)])
+        };
+        let (bulls, cows) = (count(bulls, Key::Bull, Key::Bulls), count(cows, Key::Cow, Key::Cows));
+        let mut answer = locale.format(Key::Score, &[("bulls", &bulls), ("cows", &cows)]);
+        if self.max_attempts.is_some_and(|max| self.attempts >= max) {
+            self.outcome = Some(Outcome::Lost);
+            answer.push('\n');
+            answer.push_str(&locale.format(Key::CodeLost, &[("code", &self.secret_text())]));
+        }
+        Ok(answer)
+    }
+
+    fn describe_error(&self, error: &GuessError) -> String {
+        self.locale.guess_error(error)
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/*
+    The roles reversed: the player thinks of a number and the computer guesses it, using any
+    solver Strategy, from the player's higher/lower replies. The outcome is Won once the number
+    is found, and Lost if the replies contradict each other (no number fits all of them).
+ */
+pub struct Reverse<S: Strategy> {
+    strategy: S,
+    range: RangeInclusive<i32>,
+    low: i32,
+    high: i32,
+    current: i32,
+    attempts: u32,
+    outcome: Option<Outcome>,
+    locale: Locale,
+}
+
+impl<S: Strategy> Reverse<S> {
+    pub fn new(mut strategy: S, range: RangeInclusive<i32>) -> Reverse<S> {
+        strategy.start(&range);
+        let current = strategy.next_guess();
+
+        Reverse {
+            strategy,
+            low: *range.start(),
+            high: *range.end(),
+            range,
+            current,
+            attempts: 0,
+            outcome: None,
+            locale: Locale::default(),
+        }
+    }
+
+    /// Talks to the player in `locale` instead of English; the replies stay higher, lower and yes.
+    pub fn with_locale(self, locale: Locale) -> Reverse<S> {
+        Reverse { locale, ..self }
+    }
+
+    fn parse_reply(input: &str) -> Result<Feedback, GuessError> {
+        match input.trim().to_lowercase().as_str() {
+            "higher" | "h" | "+" => Ok(Feedback::TooSmall),
+            "lower" | "l" | "-" => Ok(Feedback::TooBig),
+            "yes" | "y" | "correct" => Ok(Feedback::Correct),
+            other => Err(GuessError::Reply { input: String::from(other) }),
+        }
+    }
+}
+
+impl<S: Strategy> Variant for Reverse<S> {
+    fn introduction(&self) -> Vec<String> {
+        let (min, max) = (self.range.start(), self.range.end());
+        vec![
+            self.locale.format(Key::ReverseRules, &[("min", min), ("max", max)]),
+            self.locale.message(Key::ReverseReplies),
+        ]
+    }
+
+    fn prompt(&self) -> String {
+        self.locale.format(Key::ReversePrompt, &[("guess", &self.current)])
+    }
+
+    fn take_turn(&mut self, input: &str) -> Result<String, GuessError> {
+        if self.outcome.is_some() {
+            return Err(GuessError::GameOver);
+        }
+        let feedback = Reverse::<S>::parse_reply(input)?;
+        self.attempts += 1;
+
+        // Higher than i32::MAX or lower than i32::MIN, there is no number left at all.
+        let bounds = match feedback {
+            Feedback::Correct => {
+                self.outcome = Some(Outcome::Won);
+                let (guess, attempts) = (self.current, self.attempts);
+                return Ok(self.locale.format(Key::ReverseWin, &[("guess", &guess), ("attempts", &attempts)]));
+            }
+            Feedback::TooSmall => self.current.checked_add(1).map(|above| (self.low.max(above), self.high)),
+            Feedback::TooBig => self.current.checked_sub(1).map(|below| (self.low, self.high.min(below))),
+        };
+
+        match bounds {
+            Some((low, high)) if low <= high => (self.low, self.high) = (low, high),
+            _ => {
+                self.outcome = Some(Outcome::Lost);
+                return Ok(self.locale.message(Key::Contradiction));
+            }
+        }
+        self.strategy.learn(self.current, feedback);
+        self.current = self.strategy.next_guess();
+        Ok(String::new())
+    }
+
+    fn describe_error(&self, error: &GuessError) -> String {
+        self.locale.guess_error(error)
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guessing_game::solver::BinarySearch;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn transcript<V: Variant>(variant: &mut V, input: &str) -> (Outcome, String) {
+        let mut output = vec![];
+        let outcome = play_variant(variant, input.as_bytes(), &mut output).unwrap();
+        (outcome, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn hot_cold_reports_distance_and_validates_the_range() {
+        let mut variant = HotCold::new(&mut StdRng::seed_from_u64(3), Settings::new(1..=100, None));
+        let secret = variant.game.secret_number;
+        let far = if secret > 50 { 1 } else { 100 };
+        let input = format!("101\n{far}\n{}\n{secret}\n", secret + if secret > 50 { -1 } else { 1 });
+
+        let (outcome, output) = transcript(&mut variant, &input);
+
+        assert_eq!(outcome, Outcome::Won);
+        assert!(output.contains("The guess must be between 1 and 100, got 101.\n"));
+        assert!(output.contains("Cold.\n") || output.contains("Freezing!\n"));
+        assert!(output.contains("Boiling!\nPlease input your guess.\nYou win!\n"));
+        assert_eq!(variant.attempts(), 3);
+    }
+
+    #[test]
+    fn hot_cold_speaks_the_locale_of_the_settings() {
+        let settings = Settings::new(1..=100, Some(1)).with_locale(Locale::German);
+        let mut variant = HotCold::new(&mut StdRng::seed_from_u64(3), settings);
+        let secret = variant.game.secret_number;
+        let wrong = if secret == 1 { 2 } else { 1 };
+
+        let (outcome, output) = transcript(&mut variant, &format!("0\n{wrong}\n"));
+
+        assert_eq!(outcome, Outcome::Lost);
+        assert!(output.starts_with("Heiß oder kalt?\nDie Zahl liegt zwischen 1 und 100.\nDu hast 1 Versuche.\n"));
+        assert!(output.contains("Je näher du kommst, desto heißer wird es.\nBitte gib deinen Tipp ein.\n"));
+        assert!(output.contains("Der Tipp muss zwischen 1 und 100 liegen, nicht 0.\n"));
+        assert!(output.ends_with(&format!("Verloren! Die Zahl war {secret}.\n")));
+    }
+
+    #[test]
+    fn counts_bulls_and_cows() {
+        assert_eq!(bulls_and_cows(&[1, 2, 3, 4], &[1, 2, 3, 4]), (4, 0));
+        assert_eq!(bulls_and_cows(&[1, 2, 3, 4], &[4, 3, 2, 1]), (0, 4));
+        assert_eq!(bulls_and_cows(&[1, 2, 3, 4], &[1, 5, 2, 6]), (1, 1));
+        assert_eq!(bulls_and_cows(&[1, 2, 3, 4], &[5, 6, 7, 8]), (0, 0));
+    }
+
+    #[test]
+    fn bulls_and_cows_game_validates_codes_and_limits_attempts() {
+        let mut variant = BullsAndCows::new(&mut StdRng::seed_from_u64(4), 4, Some(2));
+        let secret = variant.secret_text();
+        let wrong: String = secret.chars().rev().collect();
+
+        let (outcome, output) = transcript(&mut variant, &format!("12\n1123\n12a4\n{wrong}\n{wrong}\n"));
+
+        assert_eq!(outcome, Outcome::Lost);
+        assert_eq!(output.matches("is not a code of 4 different digits.").count(), 3);
+        assert!(output.contains("0 bulls, 4 cows.\n"));
+        assert!(output.ends_with(&format!("You lose! The code was {secret}.\n")));
+    }
+
+    #[test]
+    fn bulls_and_cows_speaks_its_locale() {
+        let variant = BullsAndCows::new(&mut StdRng::seed_from_u64(4), 4, Some(1));
+        let mut variant = variant.with_locale(Locale::German);
+        let secret = variant.secret_text();
+        let wrong: String = secret.chars().rev().collect();
+
+        let (outcome, output) = transcript(&mut variant, &format!("12\n{wrong}\n"));
+
+        assert_eq!(outcome, Outcome::Lost);
+        assert!(output.starts_with("Bullen und Kühe!\nErrate den Code aus 4 verschiedenen Ziffern.\n"));
+        assert!(output.contains("Du hast 1 Versuche.\nBitte gib deinen Code ein.\n"));
+        assert!(output.contains("`12` ist kein Code aus 4 verschiedenen Ziffern.\n"));
+        assert!(output.ends_with(&format!("0 Bullen, 4 Kühe.\nVerloren! Der Code war {secret}.\n")));
+    }
+
+    #[test]
+    fn reverse_mode_finds_the_players_number() {
+        let mut variant = Reverse::new(BinarySearch::new(), 1..=100);
+
+        // Someone who picked 77 answers 50, 75, 88, 81, 78, 76 and finally 77.
+        let (outcome, output) = transcript(&mut variant, "maybe\nhigher\nh\nlower\nl\n-\n+\nyes\n");
+
+        assert_eq!(outcome, Outcome::Won);
+        assert!(output.starts_with("Think of a number between 1 and 100, and I will guess it.\n"));
+        assert!(output.contains("Is it 50?\nPlease answer higher, lower or yes.\nIs it 50?\n"));
+        assert!(output.ends_with("Is it 77?\nGot it: 77 in 7 guesses!\n"));
+    }
+
+    #[test]
+    fn reverse_mode_speaks_its_locale() {
+        let mut variant = Reverse::new(BinarySearch::new(), 1..=10).with_locale(Locale::German);
+
+        let (outcome, output) = transcript(&mut variant, "vielleicht\nh\nyes\n");
+
+        assert_eq!(outcome, Outcome::Won);
+        assert_eq!(
+            output,
+            "Denk dir eine Zahl zwischen 1 und 10 aus, und ich errate sie.\n\
+             Antworte auf jeden Tipp mit higher, lower oder yes.\n\
+             Ist es 5?\nBitte antworte mit higher, lower oder yes.\n\
+             Ist es 5?\nIst es 8?\nErraten: 8 nach 2 Tipps!\n"
+        );
+    }
+
+    #[test]
+    fn reverse_mode_notices_contradictions() {
+        let mut variant = Reverse::new(BinarySearch::new(), 1..=10);
+
+        // 5? higher. 8? lower. 6? higher. 7? lower: nothing is left between 7 and 6.
+        let (outcome, output) = transcript(&mut variant, "h\nl\nh\nl\n");
+
+        assert_eq!(outcome, Outcome::Lost);
+        assert!(output.ends_with("Is it 7?\nThat can't be right: no number fits all of your answers.\n"));
+    }
+
+    #[test]
+    fn reverse_mode_has_no_numbers_past_the_ends_of_i32() {
+        const CONTRADICTION: &str = "That can't be right: no number fits all of your answers.";
+
+        // MAX - 1? higher. MAX? higher.
+        let mut top = Reverse::new(BinarySearch::new(), i32::MAX - 2..=i32::MAX);
+        let (outcome, output) = transcript(&mut top, "h\nh\n");
+        assert_eq!(outcome, Outcome::Lost);
+        assert!(output.ends_with(&format!("Is it {}?\n{CONTRADICTION}\n", i32::MAX)));
+
+        // MIN + 1? lower. MIN? lower.
+        let mut bottom = Reverse::new(BinarySearch::new(), i32::MIN..=i32::MIN + 2);
+        let (outcome, output) = transcript(&mut bottom, "l\nl\n");
+        assert_eq!(outcome, Outcome::Lost);
+        assert!(output.ends_with(&format!("Is it {}?\n{CONTRADICTION}\n", i32::MIN)));
+    }
+
+    #[test]
+    fn finished_games_refuse_more_turns() {
+        let mut won = BullsAndCows::new(&mut StdRng::seed_from_u64(4), 4, None);
+        let secret = won.secret_text();
+        assert_eq!(transcript(&mut won, &format!("{secret}\n")).0, Outcome::Won);
+        assert_eq!(won.take_turn(&secret), Err(GuessError::GameOver));
+
+        let mut lost = BullsAndCows::new(&mut StdRng::seed_from_u64(4), 4, Some(1));
+        let wrong: String = secret.chars().rev().collect();
+        assert_eq!(transcript(&mut lost, &format!("{wrong}\n")).0, Outcome::Lost);
+        assert_eq!(lost.take_turn(&secret), Err(GuessError::GameOver));
+
+        let mut won = Reverse::new(BinarySearch::new(), 1..=100);
+        assert_eq!(transcript(&mut won, "yes\n").0, Outcome::Won);
+        assert_eq!(won.take_turn("yes"), Err(GuessError::GameOver));
+
+        let mut lost = Reverse::new(BinarySearch::new(), 1..=1);
+        assert_eq!(transcript(&mut lost, "higher\n").0, Outcome::Lost);
+        assert_eq!(lost.take_turn("yes"), Err(GuessError::GameOver));
+        assert_eq!(lost.attempts(), 1);
+    }
+}
@@ -1,38 +1,154 @@
+use std::fmt;
+use std::time::SystemTime;
+
+pub mod concurrent;
+pub mod messengers;
+pub mod quota;
+pub mod rate_limit;
+
+pub use self::concurrent::ConcurrentLimitTracker;
+pub use self::messengers::{
+    ChannelMessenger, FileMessenger, MessengerFanout, SinkError, SyncMessengerFanout, WebhookMessenger,
+};
+pub use self::quota::QuotaRegistry;
+pub use self::rate_limit::Acquire;
+pub use crate::clock::{Clock, ManualClock, SystemClock};
+
 pub trait Messenger {
     fn send(&self, msg: &str);
 }
 
-pub struct LimitTracker<'a, T: Messenger> {
+/// How close a tracked value is to its maximum, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// At least 75% of the quota is used.
+    Warning,
+    /// At least 90% of the quota is used.
+    Urgent,
+    /// The quota is used up.
+    Exceeded,
+}
+
+impl Severity {
+    /// Returns the severity for a usage ratio (`value / max`), or `None` below 75%.
+    pub fn for_ratio(ratio: f64) -> Option<Severity> {
+        if ratio >= 1.0 {
+            Some(Severity::Exceeded)
+        } else if ratio >= 0.9 {
+            Some(Severity::Urgent)
+        } else if ratio >= 0.75 {
+            Some(Severity::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// A structured quota alert, so receivers don't have to parse English to find the severity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub severity: Severity,
+    pub quota: String,
+    pub value: usize,
+    pub max: usize,
+    /// `value` as a percentage of `max` (100.0 means the quota is exactly used up).
+    pub percentage: f64,
+    pub timestamp: SystemTime,
+}
+
+// The Display text is what Messenger implementations have always received.
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Exceeded => write!(f, "Error: You are over your quota!"),
+            Severity::Urgent => write!(f, "Urgent warning: You've used up over 90% of your quota!"),
+            Severity::Warning => write!(f, "Warning: You've used up over 75% of your quota!"),
+        }
+    }
+}
+
+/// Receives structured alerts from a `LimitTracker`.
+pub trait AlertSink {
+    fn alert(&self, alert: &Alert);
+
+    /// Delivers `alert` and reports whether that worked.
+    ///
+    /// The default calls `alert` and always succeeds; sinks that can fail (files, sockets, ...)
+    /// override it, and their `alert` is expected to call it and swallow the error.
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        self.alert(alert);
+        Ok(())
+    }
+}
+
+/*
+    Text adapter: every Messenger is also an AlertSink that receives the alert rendered through
+    Display. Existing Messenger implementations keep working with LimitTracker unchanged, while
+    new receivers can implement AlertSink directly and match on alert.severity instead.
+ */
+impl<T: Messenger + ?Sized> AlertSink for T {
+    fn alert(&self, alert: &Alert) {
+        self.send(&alert.to_string());
+    }
+}
+
+pub struct LimitTracker<'a, T: AlertSink + ?Sized> {
     messenger: &'a T,
+    name: String,
     value: usize,
     max: usize,
+    mode: rate_limit::Mode<'a>,
 }
 
 impl<'a, T> LimitTracker<'a, T>
     where
-        T: Messenger,
+        T: AlertSink + ?Sized,
 {
     pub fn new(messenger: &'a T, max: usize) -> LimitTracker<'a, T> {
+        LimitTracker::named(messenger, "quota", max)
+    }
+
+    /// Creates a tracker whose alerts carry `name` as the quota name.
+    pub fn named(messenger: &'a T, name: &str, max: usize) -> LimitTracker<'a, T> {
         LimitTracker {
             messenger,
+            name: String::from(name),
             value: 0,
             max,
+            mode: rate_limit::Mode::Static,
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> usize {
+        self.value
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
     pub fn set_value(&mut self, value: usize) {
         self.value = value;
+        self.report(value);
+    }
 
-        let percentage_of_max = self.value as f64 / self.max as f64;
+    // Alerts if `value` is close to or over the maximum.
+    fn report(&self, value: usize) {
+        let percentage_of_max = value as f64 / self.max as f64;
 
-        if percentage_of_max >= 1.0 {
-            self.messenger.send("Error: You are over your quota!");
-        } else if percentage_of_max >= 0.9 {
-            self.messenger
-                .send("Urgent warning: You've used up over 90% of your quota!");
-        } else if percentage_of_max >= 0.75 {
-            self.messenger
-                .send("Warning: You've used up over 75% of your quota!");
+        if let Some(severity) = Severity::for_ratio(percentage_of_max) {
+            self.messenger.alert(&Alert {
+                severity,
+                quota: self.name.clone(),
+                value,
+                max: self.max,
+                percentage: percentage_of_max * 100.0,
+                timestamp: SystemTime::now(),
+            });
         }
     }
 }
@@ -41,6 +157,50 @@ impl<'a, T> LimitTracker<'a, T>
 mod tests {
     use super::*;
     use std::cell::RefCell;
+    use std::sync::Mutex;
+
+    /// Records every alert it receives; shared by the tests of all the trackers and sinks.
+    /// It sits behind a Mutex rather than a RefCell so the concurrent tests can use it too.
+    pub(crate) struct RecordingSink {
+        alerts: Mutex<Vec<Alert>>,
+    }
+
+    impl RecordingSink {
+        pub(crate) fn new() -> RecordingSink {
+            RecordingSink { alerts: Mutex::new(vec![]) }
+        }
+
+        pub(crate) fn alerts(&self) -> Vec<Alert> {
+            self.alerts.lock().unwrap().clone()
+        }
+
+        /// The quota name and severity of every alert, in the order they arrived.
+        pub(crate) fn received(&self) -> Vec<(String, Severity)> {
+            self.alerts().into_iter().map(|alert| (alert.quota, alert.severity)).collect()
+        }
+
+        pub(crate) fn count(&self, severity: Severity) -> usize {
+            self.alerts().iter().filter(|alert| alert.severity == severity).count()
+        }
+    }
+
+    impl AlertSink for RecordingSink {
+        fn alert(&self, alert: &Alert) {
+            self.alerts.lock().unwrap().push(alert.clone());
+        }
+    }
+
+    // An alert for the sink tests in the submodules.
+    pub(crate) fn sample_alert() -> Alert {
+        Alert {
+            severity: Severity::Urgent,
+            quota: String::from("acme/\"disk\""),
+            value: 95,
+            max: 100,
+            percentage: 95.0,
+            timestamp: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+        }
+    }
 
     struct MockMessenger {
         sent_messages: RefCell<Vec<String>>,
@@ -81,4 +241,37 @@ mod tests {
 
         assert_eq!(mock_messenger.sent_messages.borrow().len(), 1);
     }
+
+    #[test]
+    fn messenger_receives_the_rendered_alert_text() {
+        let mock_messenger = MockMessenger::new();
+        let mut limit_tracker = LimitTracker::new(&mock_messenger, 100);
+
+        limit_tracker.set_value(95);
+        limit_tracker.set_value(100);
+
+        assert_eq!(
+            *mock_messenger.sent_messages.borrow(),
+            vec![
+                "Urgent warning: You've used up over 90% of your quota!",
+                "Error: You are over your quota!",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_emits_structured_alerts() {
+        let sink = RecordingSink::new();
+        let mut limit_tracker = LimitTracker::named(&sink, "storage", 200);
+
+        limit_tracker.set_value(100);
+        limit_tracker.set_value(160);
+
+        let alerts = sink.alerts();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, Severity::Warning);
+        assert_eq!(alerts[0].quota, "storage");
+        assert_eq!((alerts[0].value, alerts[0].max), (160, 200));
+        assert_eq!(alerts[0].percentage, 80.0);
+    }
 }
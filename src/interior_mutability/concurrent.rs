@@ -0,0 +1,165 @@
+use super::{Alert, AlertSink, Severity};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/*
+    LimitTracker needs &mut self to change its value, so it can only be used from one thread at
+    a time. ConcurrentLimitTracker keeps its value in an AtomicUsize instead: increment and
+    decrement take &self, so the tracker can be shared by reference (with thread::scope or an Arc)
+    between all the worker threads that consume the quota.
+
+    Alerts are sent on threshold crossings rather than on every update. Each update atomically
+    moves the value from `old` to `new`, and no two updates ever see the same `old`, so an
+    upward crossing of the 75%, 90% or 100% mark belongs to exactly one update, which sends
+    exactly one alert for it, however many threads race. A threshold that is crossed again after
+    usage dropped below it is a new crossing and alerts again.
+ */
+pub struct ConcurrentLimitTracker<'a, T: AlertSink + Sync + ?Sized> {
+    messenger: &'a T,
+    name: String,
+    value: AtomicUsize,
+    max: usize,
+}
+
+impl<'a, T> ConcurrentLimitTracker<'a, T>
+    where
+        T: AlertSink + Sync + ?Sized,
+{
+    pub fn new(messenger: &'a T, max: usize) -> ConcurrentLimitTracker<'a, T> {
+        ConcurrentLimitTracker::named(messenger, "quota", max)
+    }
+
+    pub fn named(messenger: &'a T, name: &str, max: usize) -> ConcurrentLimitTracker<'a, T> {
+        ConcurrentLimitTracker {
+            messenger,
+            name: String::from(name),
+            value: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> usize {
+        self.value.load(Ordering::SeqCst)
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Adds `delta` to the value (saturating at `usize::MAX`) and returns the new value.
+    pub fn increment(&self, delta: usize) -> usize {
+        self.update(|value| value.saturating_add(delta))
+    }
+
+    /// Subtracts `delta` from the value (saturating at zero) and returns the new value.
+    pub fn decrement(&self, delta: usize) -> usize {
+        self.update(|value| value.saturating_sub(delta))
+    }
+
+    fn update(&self, change: impl Fn(usize) -> usize) -> usize {
+        // The closure always returns Some, so fetch_update cannot fail.
+        let old = self
+            .value
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| Some(change(value)))
+            .unwrap_or_else(|value| value);
+        let new = change(old);
+
+        let before = self.severity(old);
+        let after = self.severity(new);
+        if after > before {
+            for severity in [Severity::Warning, Severity::Urgent, Severity::Exceeded] {
+                if Some(severity) > before && Some(severity) <= after {
+                    self.messenger.alert(&Alert {
+                        severity,
+                        quota: self.name.clone(),
+                        value: new,
+                        max: self.max,
+                        percentage: self.ratio(new) * 100.0,
+                        timestamp: SystemTime::now(),
+                    });
+                }
+            }
+        }
+
+        new
+    }
+
+    fn ratio(&self, value: usize) -> f64 {
+        value as f64 / self.max as f64
+    }
+
+    fn severity(&self, value: usize) -> Option<Severity> {
+        Severity::for_ratio(self.ratio(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interior_mutability::tests::RecordingSink;
+    use std::thread;
+
+    #[test]
+    fn each_crossing_alerts_exactly_once_under_contention() {
+        let sink = RecordingSink::new();
+        let tracker = ConcurrentLimitTracker::named(&sink, "requests", 8_000);
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..1_000 {
+                        tracker.increment(1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(tracker.value(), 8_000);
+        assert_eq!(sink.alerts().len(), 3);
+        assert_eq!(sink.count(Severity::Warning), 1);
+        assert_eq!(sink.count(Severity::Urgent), 1);
+        assert_eq!(sink.count(Severity::Exceeded), 1);
+    }
+
+    #[test]
+    fn racing_up_and_down_alerts_once_per_upward_crossing() {
+        let sink = RecordingSink::new();
+        let tracker = ConcurrentLimitTracker::new(&sink, 100);
+        tracker.increment(70);
+
+        // Every thread moves the value back and forth across the 75% mark 500 times.
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..500 {
+                        tracker.increment(2);
+                        tracker.decrement(2);
+                    }
+                });
+            }
+        });
+
+        // The value only ever stayed between 70 and 78, so only warnings were sent, and each
+        // one was caused by an increment that took the value from below 75 to 75 or more.
+        assert_eq!(tracker.value(), 70);
+        let alerts = sink.alerts();
+        assert!(alerts.iter().all(|alert| alert.severity == Severity::Warning));
+        assert!(alerts.iter().all(|alert| (75..=78).contains(&alert.value)));
+    }
+
+    #[test]
+    fn a_big_jump_alerts_for_every_threshold_it_crosses() {
+        let sink = RecordingSink::new();
+        let tracker = ConcurrentLimitTracker::new(&sink, 10);
+
+        assert_eq!(tracker.increment(12), 12);
+        assert_eq!(tracker.decrement(20), 0);
+
+        let severities: Vec<Severity> = sink.alerts().iter().map(|alert| alert.severity).collect();
+        assert_eq!(severities, vec![Severity::Warning, Severity::Urgent, Severity::Exceeded]);
+    }
+}
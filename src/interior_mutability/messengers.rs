@@ -0,0 +1,364 @@
+use super::{Alert, AlertSink};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+mod channel;
+mod file;
+mod webhook;
+
+pub use self::channel::ChannelMessenger;
+pub use self::file::FileMessenger;
+pub use self::webhook::WebhookMessenger;
+
+/// Why a sink could not deliver an alert.
+#[derive(Debug)]
+pub enum SinkError {
+    Io(io::Error),
+    /// The receiving end of a channel has been dropped.
+    Disconnected,
+    /// The webhook answered with a status code other than 2xx.
+    Status(u16),
+    /// The sink panicked while delivering.
+    Panicked,
+    /// Some sinks of a fan-out failed; the others still received the alert.
+    Partial { failed: usize, total: usize },
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkError::Io(error) => write!(f, "I/O error: {error}"),
+            SinkError::Disconnected => write!(f, "the receiver has been dropped"),
+            SinkError::Status(status) => write!(f, "webhook answered with status {status}"),
+            SinkError::Panicked => write!(f, "the sink panicked"),
+            SinkError::Partial { failed, total } => write!(f, "{failed} of {total} sinks failed"),
+        }
+    }
+}
+
+impl Error for SinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SinkError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SinkError {
+    fn from(error: io::Error) -> SinkError {
+        SinkError::Io(error)
+    }
+}
+
+/*
+    A fan-out is itself an AlertSink, so a single LimitTracker or QuotaRegistry can report to
+    several receivers without knowing how many there are. The sinks are trait objects because a
+    fan-out usually mixes different kinds of receivers (a log file, a webhook, a test double, ...).
+
+    Failures are isolated: every sink is called through try_alert inside catch_unwind, so a sink
+    that returns an error or even panics does not keep the alert from the sinks after it.
+    The fan-out counts the failures of each sink, and its own try_alert reports a Partial error
+    when at least one sink failed.
+
+    MessengerFanout takes any sink, including the RefCell-based test doubles, and so can only be
+    used from one thread. SyncMessengerFanout only takes sinks that are Send + Sync, and is
+    itself Sync, so a ConcurrentLimitTracker can report to it from every worker thread.
+ */
+#[derive(Default)]
+pub struct MessengerFanout<'a> {
+    sinks: Vec<Box<dyn AlertSink + 'a>>,
+    failures: Vec<Cell<usize>>,
+}
+
+impl<'a> MessengerFanout<'a> {
+    pub fn new() -> MessengerFanout<'a> {
+        MessengerFanout { sinks: vec![], failures: vec![] }
+    }
+
+    /// Adds a sink that the fan-out owns.
+    pub fn add<S: AlertSink + 'a>(&mut self, sink: S) -> &mut Self {
+        self.sinks.push(Box::new(sink));
+        self.failures.push(Cell::new(0));
+        self
+    }
+
+    /// Adds a sink that is owned elsewhere and outlives the fan-out.
+    pub fn add_ref<S: AlertSink + ?Sized>(&mut self, sink: &'a S) -> &mut Self {
+        self.add(Borrowed(sink))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// How many deliveries failed so far, per sink in the order they were added.
+    pub fn failures(&self) -> Vec<usize> {
+        self.failures.iter().map(Cell::get).collect()
+    }
+}
+
+#[derive(Default)]
+pub struct SyncMessengerFanout<'a> {
+    sinks: Vec<Box<dyn AlertSink + Send + Sync + 'a>>,
+    failures: Vec<AtomicUsize>,
+}
+
+impl<'a> SyncMessengerFanout<'a> {
+    pub fn new() -> SyncMessengerFanout<'a> {
+        SyncMessengerFanout { sinks: vec![], failures: vec![] }
+    }
+
+    /// Adds a sink that the fan-out owns.
+    pub fn add<S: AlertSink + Send + Sync + 'a>(&mut self, sink: S) -> &mut Self {
+        self.sinks.push(Box::new(sink));
+        self.failures.push(AtomicUsize::new(0));
+        self
+    }
+
+    /// Adds a sink that is owned elsewhere and outlives the fan-out.
+    pub fn add_ref<S: AlertSink + Sync + ?Sized>(&mut self, sink: &'a S) -> &mut Self {
+        self.add(Borrowed(sink))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// How many deliveries failed so far, per sink in the order they were added.
+    pub fn failures(&self) -> Vec<usize> {
+        self.failures.iter().map(|count| count.load(Ordering::Relaxed)).collect()
+    }
+}
+
+/*
+    The blanket `impl<T: Messenger> AlertSink for T` rules out a blanket impl for `&S`
+    (a downstream crate could implement Messenger for a reference), so borrowed sinks go
+    through this private wrapper instead.
+ */
+struct Borrowed<'a, S: ?Sized>(&'a S);
+
+impl<S: AlertSink + ?Sized> AlertSink for Borrowed<'_, S> {
+    fn alert(&self, alert: &Alert) {
+        self.0.alert(alert);
+    }
+
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        self.0.try_alert(alert)
+    }
+}
+
+// Delivers `alert` to every sink, calling `failed` with the index of each sink that failed.
+fn deliver<'s, S>(
+    sinks: impl ExactSizeIterator<Item = &'s S>,
+    alert: &Alert,
+    mut failed: impl FnMut(usize),
+) -> Result<(), SinkError>
+    where
+        S: AlertSink + ?Sized + 's,
+{
+    let total = sinks.len();
+    let mut failures = 0;
+
+    for (index, sink) in sinks.enumerate() {
+        let delivered = panic::catch_unwind(AssertUnwindSafe(|| sink.try_alert(alert)))
+            .unwrap_or(Err(SinkError::Panicked));
+
+        if delivered.is_err() {
+            failed(index);
+            failures += 1;
+        }
+    }
+
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(SinkError::Partial { failed: failures, total })
+    }
+}
+
+impl AlertSink for MessengerFanout<'_> {
+    fn alert(&self, alert: &Alert) {
+        let _ = self.try_alert(alert);
+    }
+
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        deliver(self.sinks.iter().map(|sink| &**sink), alert, |index| {
+            self.failures[index].set(self.failures[index].get() + 1);
+        })
+    }
+}
+
+impl AlertSink for SyncMessengerFanout<'_> {
+    fn alert(&self, alert: &Alert) {
+        let _ = self.try_alert(alert);
+    }
+
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        deliver(self.sinks.iter().map(|sink| &**sink), alert, |index| {
+            self.failures[index].fetch_add(1, Ordering::Relaxed);
+        })
+    }
+}
+
+/*
+    Sinks that can fail still have to implement the infallible AlertSink::alert. Rather than
+    printing the error from library code, they keep it here: how many deliveries failed, and the
+    error of the most recent one, for the owner to look at (or ignore) when it suits them.
+ */
+#[derive(Default)]
+pub(crate) struct FailureLog {
+    count: AtomicUsize,
+    last: Mutex<Option<SinkError>>,
+}
+
+impl FailureLog {
+    pub(crate) fn record(&self, result: Result<(), SinkError>) {
+        if let Err(error) = result {
+            self.count.fetch_add(1, Ordering::Relaxed);
+            *self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(error);
+        }
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn take_last(&self) -> Option<SinkError> {
+        self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interior_mutability::tests::RecordingSink;
+    use crate::interior_mutability::{ConcurrentLimitTracker, LimitTracker, Messenger, Severity};
+    use std::cell::RefCell;
+    use std::thread;
+
+    struct Recorder {
+        sent_messages: Mutex<Vec<String>>,
+    }
+
+    impl Recorder {
+        fn new() -> Recorder {
+            Recorder { sent_messages: Mutex::new(vec![]) }
+        }
+    }
+
+    impl Messenger for Recorder {
+        fn send(&self, msg: &str) {
+            self.sent_messages.lock().unwrap().push(String::from(msg));
+        }
+    }
+
+    struct Broken;
+
+    impl AlertSink for Broken {
+        fn alert(&self, _alert: &Alert) {}
+
+        fn try_alert(&self, _alert: &Alert) -> Result<(), SinkError> {
+            Err(SinkError::Disconnected)
+        }
+    }
+
+    struct Panicking;
+
+    impl Messenger for Panicking {
+        fn send(&self, _msg: &str) {
+            panic!("this sink always panics");
+        }
+    }
+
+    #[test]
+    fn every_sink_receives_each_alert() {
+        let first = Recorder::new();
+        let second = Recorder::new();
+        let mut fanout = MessengerFanout::new();
+        fanout.add_ref(&first).add_ref(&second);
+
+        let mut limit_tracker = LimitTracker::new(&fanout, 10);
+        limit_tracker.set_value(8);
+
+        assert_eq!(first.sent_messages.lock().unwrap().len(), 1);
+        assert_eq!(*first.sent_messages.lock().unwrap(), *second.sent_messages.lock().unwrap());
+    }
+
+    #[test]
+    fn a_failing_sink_does_not_stop_the_others() {
+        let recorder = Recorder::new();
+        let mut fanout = MessengerFanout::new();
+        fanout.add(Panicking).add(Broken).add_ref(&recorder);
+
+        let mut limit_tracker = LimitTracker::new(&fanout, 10);
+        limit_tracker.set_value(9);
+        limit_tracker.set_value(10);
+
+        assert_eq!(recorder.sent_messages.lock().unwrap().len(), 2);
+        assert_eq!(fanout.failures(), vec![2, 2, 0]);
+        assert!(matches!(
+            fanout.try_alert(&crate::interior_mutability::tests::sample_alert()),
+            Err(SinkError::Partial { failed: 2, total: 3 })
+        ));
+    }
+
+    // A single-threaded test double, like the ones the book builds with RefCell.
+    struct CellRecorder {
+        sent_messages: RefCell<Vec<String>>,
+    }
+
+    impl Messenger for CellRecorder {
+        fn send(&self, msg: &str) {
+            self.sent_messages.borrow_mut().push(String::from(msg));
+        }
+    }
+
+    #[test]
+    fn single_threaded_sinks_can_be_fanned_out() {
+        let recorder = CellRecorder { sent_messages: RefCell::new(vec![]) };
+        let mut fanout = MessengerFanout::new();
+        fanout.add_ref(&recorder).add(CellRecorder { sent_messages: RefCell::new(vec![]) });
+
+        LimitTracker::new(&fanout, 10).set_value(10);
+
+        assert_eq!(*recorder.sent_messages.borrow(), vec!["Error: You are over your quota!"]);
+        assert_eq!(fanout.failures(), vec![0, 0]);
+    }
+
+    #[test]
+    fn a_sync_fanout_is_shared_by_worker_threads() {
+        let first = RecordingSink::new();
+        let second = RecordingSink::new();
+        let mut fanout = SyncMessengerFanout::new();
+        fanout.add_ref(&first).add_ref(&second).add(Broken);
+        let tracker = ConcurrentLimitTracker::new(&fanout, 400);
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        tracker.increment(1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(first.count(Severity::Exceeded), 1);
+        assert_eq!(first.received(), second.received());
+        assert_eq!(fanout.failures(), vec![0, 0, 3]);
+    }
+}
@@ -0,0 +1,85 @@
+use super::FailureLog;
+use crate::interior_mutability::{Alert, AlertSink, SinkError};
+use std::sync::mpsc::Sender;
+
+/*
+    Forwards every alert over an mpsc channel, like the producer threads in main.rs send their
+    strings. Sender can be cloned, so several trackers (or threads) can feed the same receiver,
+    and the receiving thread decides what to do with the alerts.
+ */
+pub struct ChannelMessenger {
+    sender: Sender<Alert>,
+    failures: FailureLog,
+}
+
+impl ChannelMessenger {
+    pub fn new(sender: Sender<Alert>) -> ChannelMessenger {
+        ChannelMessenger { sender, failures: FailureLog::default() }
+    }
+
+    /// How many alerts `alert` could not send; `try_alert` reports its errors itself instead.
+    pub fn failures(&self) -> usize {
+        self.failures.count()
+    }
+
+    /// The error of the most recent failed `alert`, if there was one since the last call.
+    pub fn take_last_error(&self) -> Option<SinkError> {
+        self.failures.take_last()
+    }
+}
+
+impl AlertSink for ChannelMessenger {
+    fn alert(&self, alert: &Alert) {
+        self.failures.record(self.try_alert(alert));
+    }
+
+    // Sending only fails once the receiver has been dropped.
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        self.sender.send(alert.clone()).map_err(|_| SinkError::Disconnected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interior_mutability::tests::sample_alert;
+    use crate::interior_mutability::LimitTracker;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn alerts_arrive_on_the_receiving_thread() {
+        let (tx, rx) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let messenger = ChannelMessenger::new(tx);
+            let mut limit_tracker = LimitTracker::named(&messenger, "uploads", 4);
+            for value in 1..=4 {
+                limit_tracker.set_value(value);
+            }
+        });
+        handle.join().unwrap();
+
+        // The sender was dropped with the thread, so the iterator ends after the last alert.
+        let received: Vec<Alert> = rx.iter().collect();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].quota, "uploads");
+        assert_eq!(received[1].value, 4);
+    }
+
+    #[test]
+    fn reports_a_dropped_receiver() {
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+
+        let messenger = ChannelMessenger::new(tx);
+
+        assert!(matches!(messenger.try_alert(&sample_alert()), Err(SinkError::Disconnected)));
+        assert_eq!(messenger.failures(), 0);
+
+        messenger.alert(&sample_alert());
+        assert_eq!(messenger.failures(), 1);
+        assert!(matches!(messenger.take_last_error(), Some(SinkError::Disconnected)));
+        assert!(messenger.take_last_error().is_none());
+    }
+}
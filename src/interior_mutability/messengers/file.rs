@@ -0,0 +1,192 @@
+use super::FailureLog;
+use crate::interior_mutability::{Alert, AlertSink, SinkError};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/*
+    Appends one tab-separated line per alert to a log file:
+
+        <seconds since epoch>  <severity>  <quota>  <value>/<max>  <percentage>%  <message>
+
+    When the next line would push the file past max_bytes, the file is rotated first:
+    alerts.log becomes alerts.log.1, alerts.log.1 becomes alerts.log.2, and so on, keeping at most
+    `keep` old files. With keep == 0 the old contents are simply discarded.
+
+    The open file sits behind a Mutex, so one FileMessenger can be shared between threads.
+ */
+pub struct FileMessenger {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    state: Mutex<LogFile>,
+    failures: FailureLog,
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+impl FileMessenger {
+    pub fn new<P: AsRef<Path>>(path: P, max_bytes: u64, keep: usize) -> io::Result<FileMessenger> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(FileMessenger {
+            path,
+            max_bytes,
+            keep,
+            state: Mutex::new(LogFile { file, size }),
+            failures: FailureLog::default(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of the `n`th rotated file (`n` starts at 1 for the most recent one).
+    pub fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    /// How many alerts `alert` could not write; `try_alert` reports its errors itself instead.
+    pub fn failures(&self) -> usize {
+        self.failures.count()
+    }
+
+    /// The error of the most recent failed `alert`, if there was one since the last call.
+    pub fn take_last_error(&self) -> Option<SinkError> {
+        self.failures.take_last()
+    }
+
+    fn rotate(&self, log: &mut LogFile) -> io::Result<()> {
+        log.file.flush()?;
+
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        log.file = open_append(&self.path)?;
+        log.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn log_line(alert: &Alert) -> String {
+    let seconds = alert
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    format!(
+        "{seconds}\t{:?}\t{}\t{}/{}\t{:.1}%\t{alert}\n",
+        alert.severity, alert.quota, alert.value, alert.max, alert.percentage
+    )
+}
+
+impl AlertSink for FileMessenger {
+    fn alert(&self, alert: &Alert) {
+        self.failures.record(self.try_alert(alert));
+    }
+
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        let line = log_line(alert);
+        // A poisoned lock only means another thread panicked mid-write; the file is still usable.
+        let mut log = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if log.size > 0 && log.size + line.len() as u64 > self.max_bytes {
+            self.rotate(&mut log)?;
+        }
+
+        log.file.write_all(line.as_bytes())?;
+        log.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interior_mutability::tests::sample_alert;
+    use std::process;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust_book_{name}_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn appends_one_line_per_alert() {
+        let dir = scratch_dir("file_messenger_append");
+        let path = dir.join("alerts.log");
+        fs::write(&path, "existing line\n").unwrap();
+
+        let messenger = FileMessenger::new(&path, 1024, 2).unwrap();
+        messenger.try_alert(&sample_alert()).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            "existing line\n1700000000\tUrgent\tacme/\"disk\"\t95/100\t95.0%\t\
+             Urgent warning: You've used up over 90% of your quota!\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_and_keeps_only_the_newest_files() {
+        let dir = scratch_dir("file_messenger_rotate");
+        let path = dir.join("alerts.log");
+        let line_len = log_line(&sample_alert()).len() as u64;
+
+        // Two lines fit in one file, so five alerts leave 2 + 2 + 1 lines across three files.
+        let messenger = FileMessenger::new(&path, 2 * line_len, 1).unwrap();
+        for _ in 0..5 {
+            messenger.try_alert(&sample_alert()).unwrap();
+        }
+
+        let lines = |path: PathBuf| fs::read_to_string(path).unwrap().lines().count();
+        assert_eq!(lines(path.clone()), 1);
+        assert_eq!(lines(messenger.rotated_path(1)), 2);
+        assert!(!messenger.rotated_path(2).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn alert_keeps_the_error_instead_of_printing_it() {
+        let dir = scratch_dir("file_messenger_failure");
+        let path = dir.join("alerts.log");
+        let messenger = FileMessenger::new(&path, 1, 1).unwrap();
+        messenger.alert(&sample_alert());
+
+        // Rotating needs to rename the log, which fails once its directory is gone.
+        fs::remove_dir_all(&dir).unwrap();
+        messenger.alert(&sample_alert());
+
+        assert_eq!(messenger.failures(), 1);
+        assert!(matches!(messenger.take_last_error(), Some(SinkError::Io(_))));
+        assert!(messenger.take_last_error().is_none());
+    }
+}
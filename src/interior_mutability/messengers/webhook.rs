@@ -0,0 +1,284 @@
+use super::FailureLog;
+use crate::interior_mutability::{Alert, AlertSink, SinkError};
+use crate::json;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/*
+    POSTs every alert as a small JSON document to an http:// URL, speaking just enough HTTP/1.1
+    over a plain TcpStream to do so (one request per connection, Connection: close).
+
+    Connection errors, timeouts and 5xx/429 answers are retried up to max_attempts times in
+    total, waiting backoff, 2 * backoff, 4 * backoff, ... between attempts. Any other non-2xx
+    status means the receiver rejected the alert, so retrying would not help.
+ */
+pub struct WebhookMessenger {
+    authority: String,
+    path: String,
+    max_attempts: u32,
+    backoff: Duration,
+    timeout: Duration,
+    failures: FailureLog,
+}
+
+impl WebhookMessenger {
+    /// Accepts URLs of the form `http://host[:port][/path]`.
+    pub fn new(url: &str) -> io::Result<WebhookMessenger> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("not an http:// URL: {url}"));
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(invalid());
+        }
+        let authority = if authority.contains(':') {
+            String::from(authority)
+        } else {
+            format!("{authority}:80")
+        };
+
+        Ok(WebhookMessenger {
+            authority,
+            path: String::from(path),
+            max_attempts: 3,
+            backoff: Duration::from_millis(100),
+            timeout: Duration::from_secs(5),
+            failures: FailureLog::default(),
+        })
+    }
+
+    pub fn with_retries(mut self, max_attempts: u32, backoff: Duration) -> WebhookMessenger {
+        self.max_attempts = max_attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    /// Limits how long connecting, sending and waiting for the answer may take per attempt.
+    pub fn with_timeout(mut self, timeout: Duration) -> WebhookMessenger {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How many alerts `alert` could not deliver; `try_alert` reports its errors itself instead.
+    pub fn failures(&self) -> usize {
+        self.failures.count()
+    }
+
+    /// The error of the most recent failed `alert`, if there was one since the last call.
+    pub fn take_last_error(&self) -> Option<SinkError> {
+        self.failures.take_last()
+    }
+
+    fn post(&self, body: &str) -> Result<u16, SinkError> {
+        let address = self
+            .authority
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host has no address"))?;
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.path,
+            self.authority,
+            body.len()
+        )?;
+        stream.flush()?;
+
+        // Only the status line matters: "HTTP/1.1 204 No Content"
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| {
+                SinkError::Io(io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP status line"))
+            })
+    }
+}
+
+fn retryable(error: &SinkError) -> bool {
+    match error {
+        SinkError::Status(status) => *status >= 500 || *status == 429,
+        _ => true,
+    }
+}
+
+fn json_body(alert: &Alert) -> String {
+    let seconds = alert
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    json::object(&[
+        ("severity", &json::string(&format!("{:?}", alert.severity))),
+        ("quota", &json::string(&alert.quota)),
+        ("value", &alert.value),
+        ("max", &alert.max),
+        ("percentage", &json::number(format!("{:.1}", alert.percentage))),
+        ("timestamp", &seconds),
+        ("message", &json::string(&alert.to_string())),
+    ])
+}
+
+impl AlertSink for WebhookMessenger {
+    fn alert(&self, alert: &Alert) {
+        self.failures.record(self.try_alert(alert));
+    }
+
+    fn try_alert(&self, alert: &Alert) -> Result<(), SinkError> {
+        let body = json_body(alert);
+        let mut delay = self.backoff;
+
+        for attempt in 1..=self.max_attempts {
+            let error = match self.post(&body) {
+                Ok(status) if (200..300).contains(&status) => return Ok(()),
+                Ok(status) => SinkError::Status(status),
+                Err(error) => error,
+            };
+
+            if attempt == self.max_attempts || !retryable(&error) {
+                return Err(error);
+            }
+            thread::sleep(delay);
+            delay *= 2;
+        }
+
+        unreachable!("max_attempts is at least 1")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interior_mutability::tests::sample_alert;
+    use crate::interior_mutability::LimitTracker;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /*
+        An in-process stand-in for the webhook receiver: it answers the next connections with the
+        given status codes, one per connection, and returns the request bodies it received.
+     */
+    fn stand_in(statuses: Vec<u16>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/quota", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut bodies = vec![];
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                assert_eq!(request_line, "POST /hooks/quota HTTP/1.1\r\n");
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = header.strip_prefix("Content-Length: ") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+
+                write!(reader.get_mut(), "HTTP/1.1 {status} Stand-in\r\nContent-Length: 0\r\n\r\n").unwrap();
+            }
+            bodies
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn retries_server_errors_until_delivered() {
+        let (url, server) = stand_in(vec![503, 500, 204]);
+        let messenger = WebhookMessenger::new(&url)
+            .unwrap()
+            .with_retries(3, Duration::from_millis(1));
+
+        messenger.try_alert(&sample_alert()).unwrap();
+
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(
+            bodies[2],
+            concat!(
+                r#"{"severity": "Urgent", "quota": "acme/\"disk\"", "value": 95, "max": 100, "percentage": 95.0, "#,
+                r#""timestamp": 1700000000, "message": "Urgent warning: You've used up over 90% of your quota!"}"#
+            )
+        );
+    }
+
+    // A quota of 0 is exceeded by any use at all, by an infinite percentage.
+    #[test]
+    fn sends_an_infinite_percentage_as_null() {
+        let (url, server) = stand_in(vec![204]);
+        let messenger = WebhookMessenger::new(&url).unwrap();
+
+        let mut quota = LimitTracker::new(&messenger, 0);
+        quota.set_value(1);
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].contains(r#""max": 0, "percentage": null, "#), "{}", bodies[0]);
+        assert_eq!(messenger.failures(), 0);
+    }
+
+    #[test]
+    fn does_not_retry_a_rejected_alert() {
+        let (url, server) = stand_in(vec![400]);
+        let messenger = WebhookMessenger::new(&url)
+            .unwrap()
+            .with_retries(5, Duration::from_millis(1));
+
+        assert!(matches!(messenger.try_alert(&sample_alert()), Err(SinkError::Status(400))));
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let (url, server) = stand_in(vec![500, 502]);
+        let messenger = WebhookMessenger::new(&url)
+            .unwrap()
+            .with_retries(2, Duration::from_millis(1));
+
+        assert!(matches!(messenger.try_alert(&sample_alert()), Err(SinkError::Status(502))));
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_urls_it_cannot_speak_to() {
+        assert!(WebhookMessenger::new("https://example.com/hook").is_err());
+        assert!(WebhookMessenger::new("http:///hook").is_err());
+    }
+
+    #[test]
+    fn alert_keeps_the_error_instead_of_printing_it() {
+        let (url, server) = stand_in(vec![400]);
+        let messenger = WebhookMessenger::new(&url).unwrap();
+
+        messenger.alert(&sample_alert());
+        server.join().unwrap();
+
+        assert_eq!(messenger.failures(), 1);
+        assert!(matches!(messenger.take_last_error(), Some(SinkError::Status(400))));
+    }
+}
@@ -0,0 +1,340 @@
+use super::{AlertSink, LimitTracker};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A snapshot of how much of a quota is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub value: usize,
+    pub max: usize,
+}
+
+impl Usage {
+    pub fn remaining(&self) -> usize {
+        self.max.saturating_sub(self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaError {
+    UnknownTenant(String),
+    UnknownResource { tenant: String, resource: String },
+    /// A per-resource limit may not be larger than its tenant's total limit.
+    ResourceLimitAboveTenant { tenant: String, resource: String, max: usize, tenant_max: usize },
+    /// A tenant's total limit may not drop below what its resources already use.
+    TenantLimitBelowUsage { tenant: String, max: usize, usage: usize },
+    /// The new usage doesn't fit in a usize.
+    UsageOverflow { tenant: String, resource: String },
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaError::UnknownTenant(tenant) => write!(f, "unknown tenant `{tenant}`"),
+            QuotaError::UnknownResource { tenant, resource } => {
+                write!(f, "tenant `{tenant}` has no quota for `{resource}`")
+            }
+            QuotaError::ResourceLimitAboveTenant { tenant, resource, max, tenant_max } => write!(
+                f,
+                "limit {max} for `{tenant}/{resource}` is above the tenant limit {tenant_max}"
+            ),
+            QuotaError::TenantLimitBelowUsage { tenant, max, usage } => {
+                write!(f, "limit {max} for `{tenant}` is below its current usage {usage}")
+            }
+            QuotaError::UsageOverflow { tenant, resource } => {
+                write!(f, "usage of `{tenant}/{resource}` overflowed")
+            }
+        }
+    }
+}
+
+impl Error for QuotaError {}
+
+type Tracker<'a> = LimitTracker<'a, dyn AlertSink + 'a>;
+
+struct Tenant<'a> {
+    total: Tracker<'a>,
+    resources: HashMap<String, Tracker<'a>>,
+}
+
+/*
+    Quotas are hierarchical: each tenant has a total limit, and each of its resources has its own
+    limit underneath it. Every level is an ordinary LimitTracker sharing one AlertSink, so the
+    alerting rules are the same as for a single tracker. Use a MessengerFanout as the sink to
+    deliver the alerts to several receivers.
+
+    The tenant's usage is always the sum of its resources' usage; it is recomputed whenever a
+    resource changes, and the tenant tracker alerts on that sum under the tenant's own name.
+ */
+pub struct QuotaRegistry<'a> {
+    messenger: &'a (dyn AlertSink + 'a),
+    tenants: HashMap<String, Tenant<'a>>,
+}
+
+impl<'a> QuotaRegistry<'a> {
+    pub fn new<S: AlertSink>(messenger: &'a S) -> QuotaRegistry<'a> {
+        QuotaRegistry {
+            messenger,
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Registers `tenant` with a total limit, or changes the limit of a known tenant.
+    ///
+    /// A known tenant's new limit must still hold its resources: it can't be below any of their
+    /// limits, nor below what they use together.
+    pub fn set_tenant_limit(&mut self, tenant: &str, max: usize) -> Result<(), QuotaError> {
+        let Some(existing) = self.tenants.get_mut(tenant) else {
+            let total = LimitTracker::named(self.messenger, tenant, max);
+            self.tenants.insert(String::from(tenant), Tenant { total, resources: HashMap::new() });
+            return Ok(());
+        };
+
+        if let Some((resource, tracker)) = existing.resources.iter().find(|(_, tracker)| tracker.max > max) {
+            return Err(QuotaError::ResourceLimitAboveTenant {
+                tenant: String::from(tenant),
+                resource: resource.clone(),
+                max: tracker.max,
+                tenant_max: max,
+            });
+        }
+        if existing.total.value > max {
+            return Err(QuotaError::TenantLimitBelowUsage {
+                tenant: String::from(tenant),
+                max,
+                usage: existing.total.value,
+            });
+        }
+        existing.total.max = max;
+        Ok(())
+    }
+
+    /// Registers a per-resource limit under an existing tenant, or changes it.
+    pub fn set_resource_limit(&mut self, tenant: &str, resource: &str, max: usize) -> Result<(), QuotaError> {
+        let messenger = self.messenger;
+        let entry = self
+            .tenants
+            .get_mut(tenant)
+            .ok_or_else(|| QuotaError::UnknownTenant(String::from(tenant)))?;
+
+        if max > entry.total.max {
+            return Err(QuotaError::ResourceLimitAboveTenant {
+                tenant: String::from(tenant),
+                resource: String::from(resource),
+                max,
+                tenant_max: entry.total.max,
+            });
+        }
+
+        entry
+            .resources
+            .entry(String::from(resource))
+            .and_modify(|tracker| tracker.max = max)
+            .or_insert_with(|| LimitTracker::named(messenger, &format!("{tenant}/{resource}"), max));
+        Ok(())
+    }
+
+    /// Sets the absolute usage of a resource and returns its new usage.
+    ///
+    /// Fails without changing anything if the tenant's total usage would overflow.
+    pub fn set_usage(&mut self, tenant: &str, resource: &str, value: usize) -> Result<Usage, QuotaError> {
+        let overflow = || QuotaError::UsageOverflow {
+            tenant: String::from(tenant),
+            resource: String::from(resource),
+        };
+        let entry = self
+            .tenants
+            .get_mut(tenant)
+            .ok_or_else(|| QuotaError::UnknownTenant(String::from(tenant)))?;
+        let current = entry.resources.get(resource).ok_or_else(|| QuotaError::UnknownResource {
+            tenant: String::from(tenant),
+            resource: String::from(resource),
+        })?;
+
+        // The other resources' usage can't overflow: it is at most the current total.
+        let total = (entry.total.value - current.value).checked_add(value).ok_or_else(overflow)?;
+        let tracker = entry.resources.get_mut(resource).expect("checked above");
+        tracker.set_value(value);
+        let usage = Usage { value: tracker.value, max: tracker.max };
+        entry.total.set_value(total);
+
+        Ok(usage)
+    }
+
+    /// Adds `delta` to the usage of a resource.
+    pub fn add_usage(&mut self, tenant: &str, resource: &str, delta: usize) -> Result<Usage, QuotaError> {
+        let current = self.usage(tenant, resource)?;
+        let value = current.value.checked_add(delta).ok_or_else(|| QuotaError::UsageOverflow {
+            tenant: String::from(tenant),
+            resource: String::from(resource),
+        })?;
+        self.set_usage(tenant, resource, value)
+    }
+
+    /// Gives back `delta` units of a resource; usage never drops below zero.
+    pub fn release(&mut self, tenant: &str, resource: &str, delta: usize) -> Result<Usage, QuotaError> {
+        let current = self.usage(tenant, resource)?;
+        self.set_usage(tenant, resource, current.value.saturating_sub(delta))
+    }
+
+    pub fn usage(&self, tenant: &str, resource: &str) -> Result<Usage, QuotaError> {
+        let entry = self
+            .tenants
+            .get(tenant)
+            .ok_or_else(|| QuotaError::UnknownTenant(String::from(tenant)))?;
+        entry
+            .resources
+            .get(resource)
+            .map(|tracker| Usage { value: tracker.value, max: tracker.max })
+            .ok_or_else(|| QuotaError::UnknownResource {
+                tenant: String::from(tenant),
+                resource: String::from(resource),
+            })
+    }
+
+    pub fn tenant_usage(&self, tenant: &str) -> Result<Usage, QuotaError> {
+        self.tenants
+            .get(tenant)
+            .map(|entry| Usage { value: entry.total.value, max: entry.total.max })
+            .ok_or_else(|| QuotaError::UnknownTenant(String::from(tenant)))
+    }
+
+    /// Lists the usage of every resource of `tenant`, sorted by resource name.
+    pub fn resources(&self, tenant: &str) -> Result<Vec<(&str, Usage)>, QuotaError> {
+        let entry = self
+            .tenants
+            .get(tenant)
+            .ok_or_else(|| QuotaError::UnknownTenant(String::from(tenant)))?;
+        let mut resources: Vec<(&str, Usage)> = entry
+            .resources
+            .iter()
+            .map(|(name, tracker)| (name.as_str(), Usage { value: tracker.value, max: tracker.max }))
+            .collect();
+        resources.sort_by_key(|(name, _)| *name);
+        Ok(resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interior_mutability::tests::RecordingSink;
+    use crate::interior_mutability::{MessengerFanout, Severity};
+
+    fn registry(sink: &RecordingSink) -> QuotaRegistry<'_> {
+        let mut registry = QuotaRegistry::new(sink);
+        registry.set_tenant_limit("acme", 100).unwrap();
+        registry.set_resource_limit("acme", "cpu", 60).unwrap();
+        registry.set_resource_limit("acme", "disk", 80).unwrap();
+        registry
+    }
+
+    #[test]
+    fn tenant_usage_is_the_sum_of_its_resources() {
+        let sink = RecordingSink::new();
+        let mut registry = registry(&sink);
+
+        registry.set_usage("acme", "cpu", 30).unwrap();
+        registry.add_usage("acme", "disk", 20).unwrap();
+        registry.add_usage("acme", "disk", 5).unwrap();
+        registry.release("acme", "cpu", 10).unwrap();
+
+        assert_eq!(registry.usage("acme", "disk"), Ok(Usage { value: 25, max: 80 }));
+        assert_eq!(registry.tenant_usage("acme"), Ok(Usage { value: 45, max: 100 }));
+        assert_eq!(
+            registry.resources("acme").unwrap(),
+            vec![("cpu", Usage { value: 20, max: 60 }), ("disk", Usage { value: 25, max: 80 })]
+        );
+        assert!(sink.received().is_empty());
+    }
+
+    #[test]
+    fn alerts_name_the_level_that_crossed_a_threshold() {
+        let sink = RecordingSink::new();
+        let mut registry = registry(&sink);
+
+        registry.set_usage("acme", "cpu", 50).unwrap();
+        assert_eq!(sink.received(), vec![(String::from("acme/cpu"), Severity::Warning)]);
+
+        registry.set_usage("acme", "disk", 45).unwrap();
+        assert_eq!(sink.received()[1..], [(String::from("acme"), Severity::Urgent)]);
+    }
+
+    #[test]
+    fn rejects_unknown_names_and_oversized_resource_limits() {
+        let sink = RecordingSink::new();
+        let mut registry = registry(&sink);
+
+        assert_eq!(
+            registry.set_usage("globex", "cpu", 1),
+            Err(QuotaError::UnknownTenant(String::from("globex")))
+        );
+        assert!(matches!(
+            registry.add_usage("acme", "gpu", 1),
+            Err(QuotaError::UnknownResource { .. })
+        ));
+        assert!(matches!(
+            registry.set_resource_limit("acme", "gpu", 101),
+            Err(QuotaError::ResourceLimitAboveTenant { tenant_max: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn tenant_limits_must_hold_their_resources() {
+        let sink = RecordingSink::new();
+        let mut registry = registry(&sink);
+        registry.set_usage("acme", "cpu", 50).unwrap();
+        registry.set_usage("acme", "disk", 40).unwrap();
+
+        assert!(matches!(
+            registry.set_tenant_limit("acme", 70),
+            Err(QuotaError::ResourceLimitAboveTenant { max: 80, tenant_max: 70, .. })
+        ));
+        registry.set_resource_limit("acme", "disk", 40).unwrap();
+        assert_eq!(
+            registry.set_tenant_limit("acme", 85),
+            Err(QuotaError::TenantLimitBelowUsage { tenant: String::from("acme"), max: 85, usage: 90 })
+        );
+        assert_eq!(registry.tenant_usage("acme"), Ok(Usage { value: 90, max: 100 }));
+        registry.set_tenant_limit("acme", 90).unwrap();
+        assert_eq!(registry.tenant_usage("acme"), Ok(Usage { value: 90, max: 90 }));
+    }
+
+    #[test]
+    fn usage_that_would_overflow_is_rejected() {
+        let sink = RecordingSink::new();
+        let mut registry = registry(&sink);
+        registry.set_usage("acme", "cpu", 10).unwrap();
+
+        assert!(matches!(
+            registry.add_usage("acme", "cpu", usize::MAX),
+            Err(QuotaError::UsageOverflow { .. })
+        ));
+        assert!(matches!(
+            registry.set_usage("acme", "disk", usize::MAX),
+            Err(QuotaError::UsageOverflow { .. })
+        ));
+        assert_eq!(registry.tenant_usage("acme"), Ok(Usage { value: 10, max: 100 }));
+    }
+
+    #[test]
+    fn fanout_delivers_registry_alerts_to_every_sink() {
+        let first = RecordingSink::new();
+        let second = RecordingSink::new();
+        let mut fanout = MessengerFanout::new();
+        fanout.add_ref(&first).add_ref(&second);
+
+        let mut registry = QuotaRegistry::new(&fanout);
+        registry.set_tenant_limit("acme", 10).unwrap();
+        registry.set_resource_limit("acme", "cpu", 10).unwrap();
+        registry.set_usage("acme", "cpu", 10).unwrap();
+
+        let expected = vec![
+            (String::from("acme/cpu"), Severity::Exceeded),
+            (String::from("acme"), Severity::Exceeded),
+        ];
+        assert_eq!(first.received(), expected);
+        assert_eq!(second.received(), expected);
+    }
+}
@@ -0,0 +1,246 @@
+use super::{AlertSink, LimitTracker};
+use crate::clock::Clock;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The answer of `LimitTracker::try_acquire`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Acquire {
+    Allowed,
+    /// `retry_after` is how long to wait before the same request can succeed,
+    /// or `None` if waiting will never help.
+    Denied { retry_after: Option<Duration> },
+}
+
+impl Acquire {
+    pub fn is_allowed(&self) -> bool {
+        *self == Acquire::Allowed
+    }
+}
+
+pub(super) enum Mode<'a> {
+    /// A plain quota that only changes through set_value and try_acquire.
+    Static,
+    /// At most `max` units within any `window`, remembering when each unit was acquired.
+    Window {
+        window: Duration,
+        clock: Box<dyn Clock + 'a>,
+        log: VecDeque<(Duration, usize)>,
+    },
+    /// A bucket of `max` tokens that refills continuously at `refill_per_second`.
+    TokenBucket {
+        refill_per_second: f64,
+        clock: Box<dyn Clock + 'a>,
+        tokens: f64,
+        last_refill: Duration,
+    },
+}
+
+/*
+    In every mode `value` is the usage the tracker alerts on: the units acquired so far for a
+    static quota, the units acquired within the current window, or the tokens missing from the
+    bucket. An allowed acquire reports the new usage, so the same Messenger warns at 75% and
+    90% as for a static quota; a denied one reports the usage it would have caused, which is
+    over the maximum and therefore an Exceeded alert.
+ */
+impl<'a, T> LimitTracker<'a, T>
+    where
+        T: AlertSink + ?Sized,
+{
+    /// Allows at most `max` units within any sliding `window`.
+    pub fn rate_limited<C: Clock + 'a>(
+        messenger: &'a T,
+        name: &str,
+        max: usize,
+        window: Duration,
+        clock: C,
+    ) -> LimitTracker<'a, T> {
+        let mut tracker = LimitTracker::named(messenger, name, max);
+        tracker.mode = Mode::Window {
+            window,
+            clock: Box::new(clock),
+            log: VecDeque::new(),
+        };
+        tracker
+    }
+
+    /// A bucket that holds up to `capacity` tokens, starts full and refills at `refill_per_second`.
+    pub fn token_bucket<C: Clock + 'a>(
+        messenger: &'a T,
+        name: &str,
+        capacity: usize,
+        refill_per_second: f64,
+        clock: C,
+    ) -> LimitTracker<'a, T> {
+        let last_refill = clock.now();
+        let mut tracker = LimitTracker::named(messenger, name, capacity);
+        tracker.mode = Mode::TokenBucket {
+            refill_per_second,
+            clock: Box::new(clock),
+            tokens: capacity as f64,
+            last_refill,
+        };
+        tracker
+    }
+
+    /// Tries to use `n` units of the quota right now.
+    pub fn try_acquire(&mut self, n: usize) -> Acquire {
+        let max = self.max;
+
+        let (outcome, used) = match &mut self.mode {
+            // A sum that overflows is over any maximum.
+            Mode::Static => match self.value.checked_add(n).filter(|used| *used <= max) {
+                Some(used) => (Acquire::Allowed, used),
+                None => (Acquire::Denied { retry_after: None }, self.value),
+            },
+            Mode::Window { window, clock, log } => {
+                let now = clock.now();
+                while log.front().is_some_and(|(at, _)| *at + *window <= now) {
+                    log.pop_front();
+                }
+                let used: usize = log.iter().map(|(_, units)| units).sum();
+
+                if let Some(used) = used.checked_add(n).filter(|used| *used <= max) {
+                    log.push_back((now, n));
+                    (Acquire::Allowed, used)
+                } else {
+                    // Wait until enough of the oldest acquisitions have left the window.
+                    let mut freed = 0;
+                    let retry_after = if n > max {
+                        None
+                    } else {
+                        log.iter().find_map(|(at, units)| {
+                            freed += units;
+                            // n <= max here, so this can't underflow the way used + n could overflow
+                            (used - freed <= max - n).then(|| *at + *window - now)
+                        })
+                    };
+                    (Acquire::Denied { retry_after }, used)
+                }
+            }
+            Mode::TokenBucket { refill_per_second, clock, tokens, last_refill } => {
+                let now = clock.now();
+                let elapsed = now.saturating_sub(*last_refill).as_secs_f64();
+                *tokens = (*tokens + elapsed * *refill_per_second).min(max as f64);
+                *last_refill = now;
+
+                if *tokens >= n as f64 {
+                    *tokens -= n as f64;
+                    (Acquire::Allowed, (max as f64 - *tokens).ceil() as usize)
+                } else {
+                    let retry_after = if n > max || *refill_per_second <= 0.0 {
+                        None
+                    } else {
+                        let seconds = (n as f64 - *tokens) / *refill_per_second;
+                        Some(Duration::from_nanos((seconds * 1e9).ceil() as u64))
+                    };
+                    (Acquire::Denied { retry_after }, (max as f64 - *tokens).ceil() as usize)
+                }
+            }
+        };
+
+        self.value = used;
+        match outcome {
+            Acquire::Allowed => self.report(used),
+            Acquire::Denied { .. } => self.report(used.saturating_add(n)),
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::interior_mutability::tests::RecordingSink;
+    use crate::interior_mutability::Severity;
+
+    fn denied_for(seconds: f64) -> Acquire {
+        Acquire::Denied { retry_after: Some(Duration::from_secs_f64(seconds)) }
+    }
+
+    #[test]
+    fn huge_requests_are_denied_instead_of_overflowing() {
+        let sink = RecordingSink::new();
+        let mut quota = LimitTracker::new(&sink, usize::MAX);
+        assert!(quota.try_acquire(1).is_allowed());
+        assert_eq!(quota.try_acquire(usize::MAX), Acquire::Denied { retry_after: None });
+        assert_eq!(quota.value(), 1);
+
+        let clock = ManualClock::new();
+        let mut limiter = LimitTracker::rate_limited(&sink, "huge", usize::MAX, Duration::from_secs(1), &clock);
+        assert!(limiter.try_acquire(usize::MAX - 1).is_allowed());
+        assert_eq!(limiter.try_acquire(2), Acquire::Denied { retry_after: Some(Duration::from_secs(1)) });
+        assert_eq!(limiter.value(), usize::MAX - 1);
+    }
+
+    #[test]
+    fn window_allows_max_events_per_window() {
+        let sink = RecordingSink::new();
+        let clock = ManualClock::new();
+        let mut limiter = LimitTracker::rate_limited(&sink, "logins", 3, Duration::from_secs(10), &clock);
+
+        assert!(limiter.try_acquire(1).is_allowed());
+        clock.advance(Duration::from_secs(2));
+        assert!(limiter.try_acquire(2).is_allowed());
+        assert_eq!(limiter.try_acquire(1), denied_for(8.0));
+
+        // The first event leaves the window at t = 10s, the other two at t = 12s.
+        clock.advance(Duration::from_secs(8));
+        assert!(limiter.try_acquire(1).is_allowed());
+        assert_eq!(limiter.try_acquire(2), denied_for(2.0));
+        assert_eq!(limiter.try_acquire(4), Acquire::Denied { retry_after: None });
+
+        clock.advance(Duration::from_secs(2));
+        assert!(limiter.try_acquire(2).is_allowed());
+        assert_eq!(limiter.value(), 3);
+    }
+
+    #[test]
+    fn bucket_refills_at_the_given_rate() {
+        let sink = RecordingSink::new();
+        let clock = ManualClock::new();
+        let mut bucket = LimitTracker::token_bucket(&sink, "api", 10, 2.0, &clock);
+
+        assert!(bucket.try_acquire(10).is_allowed());
+        assert_eq!(bucket.try_acquire(3), denied_for(1.5));
+
+        clock.advance(Duration::from_millis(1_500));
+        assert!(bucket.try_acquire(3).is_allowed());
+
+        // A long pause refills the bucket, but never beyond its capacity.
+        clock.advance(Duration::from_secs(60));
+        assert!(bucket.try_acquire(10).is_allowed());
+        assert_eq!(bucket.try_acquire(11), Acquire::Denied { retry_after: None });
+    }
+
+    #[test]
+    fn warns_through_the_messenger_when_usage_nears_the_limit() {
+        let sink = RecordingSink::new();
+        let clock = ManualClock::new();
+        let mut limiter = LimitTracker::rate_limited(&sink, "uploads", 4, Duration::from_secs(1), &clock);
+
+        for _ in 0..5 {
+            limiter.try_acquire(1);
+        }
+
+        let alerts: Vec<(Severity, usize)> =
+            sink.alerts().iter().map(|alert| (alert.severity, alert.value)).collect();
+        assert_eq!(
+            alerts,
+            vec![(Severity::Warning, 3), (Severity::Exceeded, 4), (Severity::Exceeded, 5)]
+        );
+        assert_eq!(sink.alerts()[0].quota, "uploads");
+    }
+
+    #[test]
+    fn static_quotas_never_refill() {
+        let sink = RecordingSink::new();
+        let mut quota = LimitTracker::new(&sink, 10);
+
+        assert!(quota.try_acquire(6).is_allowed());
+        assert_eq!(quota.try_acquire(5), Acquire::Denied { retry_after: None });
+        assert!(quota.try_acquire(4).is_allowed());
+        assert_eq!(quota.value(), 10);
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt::Display;
+
+/*
+    The little JSON the crate writes by hand: rectangle layouts and reports, and the alerts the
+    webhook messenger posts. Strings are escaped here and only here, so every writer produces
+    the same valid JSON for names with quotes, backslashes or control characters in them.
+ */
+
+/// `text` as a JSON string, quotes included.
+pub(crate) fn string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// `value` as a JSON number, or `null` if it is infinite or NaN, which JSON has no numbers for.
+pub(crate) fn number(value: impl Display) -> String {
+    // Integers and finite floats display as valid JSON already; the others display as inf or NaN.
+    let text = value.to_string();
+    if text.parse::<f64>().is_ok_and(f64::is_finite) {
+        text
+    } else {
+        String::from("null")
+    }
+}
+
+/// The members of an object without its braces, like `"x": 1, "y": 2`. The values are written
+/// as they display, so they must be JSON already: numbers, or text from `string` or `object`.
+pub(crate) fn members(fields: &[(&str, &dyn Display)]) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| format!("{}: {value}", string(key)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An object with `fields` in order, like `{"x": 1, "y": 2}`.
+pub(crate) fn object(fields: &[(&str, &dyn Display)]) -> String {
+    format!("{{{}}}", members(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string("plain"), r#""plain""#);
+        assert_eq!(string("a \"b\" \\ c\n\t\r"), r#""a \"b\" \\ c\n\t\r""#);
+        assert_eq!(string("\u{1}\u{7f} café"), r#""\u0001\u007f café""#);
+    }
+
+    #[test]
+    fn writes_numbers_json_can_hold() {
+        assert_eq!(number(-12), "-12");
+        assert_eq!(number(u128::MAX), u128::MAX.to_string());
+        assert_eq!(number(format!("{:.1}", 95.25)), "95.2");
+        assert_eq!(number(f64::INFINITY), "null");
+        assert_eq!(number(f64::NEG_INFINITY), "null");
+        assert_eq!(number(f32::NAN), "null");
+    }
+
+    #[test]
+    fn writes_objects() {
+        assert_eq!(object(&[]), "{}");
+        assert_eq!(object(&[("name", &string("a\"b")), ("x", &-1)]), r#"{"name": "a\"b", "x": -1}"#);
+        assert_eq!(members(&[("size", &object(&[("w", &2)]))]), r#""size": {"w": 2}"#);
+    }
+}
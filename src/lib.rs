@@ -48,6 +48,8 @@ use std::io::{self, Write}; // brings std::io and std::io::Write into scope
 
 // lib.rs controls module visibility (pub => available for integration testing and external users)
 mod unit_tests;
+mod json;
+pub mod clock;
 pub mod rectangle;
 pub mod guessing_game;
 pub mod shirts;
@@ -650,23 +650,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> { // "catches" any kind of e
      */
     let s: &'static str = "I have a static lifetime.";
 
-    let store = Inventory {
-        shirts: vec![ShirtColor::Blue, ShirtColor::Red, ShirtColor::Blue],
-    };
+    let mut store = Inventory::new();
+    store.restock(Sku::new(ShirtColor::Blue, Size::Medium, Style::Crew), 2);
+    store.restock(Sku::new(ShirtColor::Red, Size::Medium, Style::Crew), 1);
 
     let user_pref1 = Some(ShirtColor::Red);
-    let giveaway1 = store.giveaway(user_pref1);
-    println!(
-        "The user with preference {:?} gets {:?}",
-        user_pref1, giveaway1
-    );
+    match store.giveaway(user_pref1) {
+        Ok(giveaway1) => println!(
+            "The user with preference {:?} gets {}",
+            user_pref1, giveaway1
+        ),
+        Err(error) => println!("The user with preference {:?} gets nothing: {error}", user_pref1),
+    }
 
     let user_pref2 = None;
-    let giveaway2 = store.giveaway(user_pref2);
-    println!(
-        "The user with preference {:?} gets {:?}",
-        user_pref2, giveaway2
-    );
+    match store.giveaway(user_pref2) {
+        Ok(giveaway2) => println!(
+            "The user with preference {:?} gets {}",
+            user_pref2, giveaway2
+        ),
+        Err(error) => println!("The user with preference {:?} gets nothing: {error}", user_pref2),
+    }
 
     let expensive_closure = |num: u32| -> u32 {
         println!("calculating slowly...");
@@ -1240,8 +1244,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> { // "catches" any kind of e
         Point3D { x: 3, y: 3, z: 1 }
     );
 
-    let meters = Meters(1);
-    let millimeters = Millimeters(500);
+    let meters = Meters(1u32);
+    let millimeters = Millimeters(500u32);
     assert_eq!(millimeters + meters, Millimeters(1500));
 
     // To disambiguate and tell Rust that we want to use the implementation of Animal for Dog as opposed
@@ -1311,17 +1315,7 @@ trait OutlinePrint: std::fmt::Display {
     }
 }
 
-#[derive(PartialEq, Debug)]
-struct Millimeters(u32);
-struct Meters(u32);
-
-impl std::ops::Add<Meters> for Millimeters {
-    type Output = Millimeters;
-
-    fn add(self, other: Meters) -> Millimeters {
-        Millimeters(self.0 + (other.0 * 1000))
-    }
-}
+// Millimeters, Meters and `impl Add<Meters> for Millimeters` come from rust_book::rectangle::units.
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 struct Point3D {
@@ -10,12 +10,122 @@
     behavior as well as how to create your own traits in Chapter 10. There are also many attributes
     other than derive; for more information, see the “Attributes” section of the Rust Reference.
  */
-#[derive(PartialEq, Debug)]
-pub struct Rectangle {
-    pub width: u32,
-    height: u32
+pub mod layout;
+pub mod packing;
+pub mod positioned;
+pub mod report;
+pub mod spatial;
+pub mod units;
+
+pub use self::positioned::{Point, Positioned};
+pub use self::units::{Meters, Millimeters};
+
+use std::fmt;
+use std::ops::Add;
+
+/*
+    A type that can be the side of a Rectangle: any primitive integer or float, or a unit-tagged
+    length like Millimeters<u32>. The area is computed in a wider type where there is one, so the
+    product of two sides can't overflow: u32 sides give a u64 area, i64 sides an i128 area.
+ */
+pub trait Dimension: Copy + PartialOrd {
+    type Area: Copy + PartialOrd + Default + Add<Output = Self::Area> + fmt::Debug + fmt::Display;
+    /// The side as a plain number, without its unit.
+    type Number: Copy + fmt::Display;
+
+    fn area(width: Self, height: Self) -> Self::Area;
+
+    /// `None` if the area can't be represented, like a float area that overflowed to infinity.
+    fn checked_area(width: Self, height: Self) -> Option<Self::Area>;
+
+    /// Computed in the wider area type too, so it can't overflow either.
+    fn perimeter(width: Self, height: Self) -> Self::Area;
+
+    /// `a + b`, stopping at the largest area instead of overflowing; for floats that is infinity.
+    fn saturating_add_area(a: Self::Area, b: Self::Area) -> Self::Area;
+
+    fn number(self) -> Self::Number;
+
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! widening_dimension {
+    ($($side:ty => $area:ty),*) => {$(
+        impl Dimension for $side {
+            type Area = $area;
+            type Number = $side;
+
+            fn area(width: $side, height: $side) -> $area {
+                <$area>::from(width) * <$area>::from(height)
+            }
+
+            fn checked_area(width: $side, height: $side) -> Option<$area> {
+                <$area>::from(width).checked_mul(<$area>::from(height))
+            }
+
+            fn perimeter(width: $side, height: $side) -> $area {
+                2 * (<$area>::from(width) + <$area>::from(height))
+            }
+
+            fn saturating_add_area(a: $area, b: $area) -> $area {
+                a.saturating_add(b)
+            }
+
+            fn number(self) -> $side {
+                self
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
+}
+
+widening_dimension!(u8 => u16, u16 => u32, u32 => u64, u64 => u128, i8 => i16, i16 => i32, i32 => i64, i64 => i128);
+
+macro_rules! float_dimension {
+    ($($side:ty),*) => {$(
+        impl Dimension for $side {
+            type Area = $side;
+            type Number = $side;
+
+            fn area(width: $side, height: $side) -> $side {
+                width * height
+            }
+
+            fn checked_area(width: $side, height: $side) -> Option<$side> {
+                Some(width * height).filter(|area| area.is_finite())
+            }
+
+            fn perimeter(width: $side, height: $side) -> $side {
+                2.0 * (width + height)
+            }
+
+            fn saturating_add_area(a: $side, b: $side) -> $side {
+                a + b
+            }
+
+            fn number(self) -> $side {
+                self
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
 }
-impl Rectangle {
+
+float_dimension!(f32, f64);
+
+// Without a type, a Rectangle has u32 sides, like it always had.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Rectangle<T = u32> {
+    pub width: T,
+    height: T
+}
+impl<T: Dimension> Rectangle<T> {
     /*
         Often, but not always, when we give a method the same name as a field we want it to only
         return the value in the field and do nothing else. Methods like this are called getters,
@@ -27,11 +137,12 @@ impl Rectangle {
         When you call a method with object.something(), Rust automatically adds in
         &, &mut, or * so object matches the signature of the method.
      */
-    pub fn new(width: u32, height: u32) -> Self {
-        Rectangle {
-            width,
-            height: dbg!(height * 2)
-        }
+    pub fn new(width: T, height: T) -> Self {
+        Rectangle { width, height }
+    }
+
+    pub fn height(&self) -> T {
+        self.height
     }
 
     /*
@@ -52,8 +163,23 @@ impl Rectangle {
         technique is usually used when the method transforms self into something else and you want
         to prevent the caller from using the original instance after the transformation.
      */
-    pub fn area(&self) -> u32 {
-        self.width * self.height
+    pub fn area(&self) -> T::Area {
+        // For u32 sides: (2^32 - 1)^2 always fits in the u64 area
+        T::area(self.width, self.height)
+    }
+
+    pub fn perimeter(&self) -> T::Area {
+        T::perimeter(self.width, self.height)
+    }
+
+    /// Width divided by height: above 1 for a landscape rectangle, below 1 for a portrait one.
+    pub fn aspect_ratio(&self) -> f64 {
+        self.width.to_f64() / self.height.to_f64()
+    }
+
+    /// The area, or `None` if it can't be represented (which integer sides never reach).
+    pub fn checked_area(&self) -> Option<T::Area> {
+        T::checked_area(self.width, self.height)
     }
 
     /*
@@ -62,17 +188,50 @@ impl Rectangle {
         otherwise, it should return false.
         if both height and width of another are <= to self, return true; else false
      */
-    pub fn can_hold(&self, another: &Rectangle) -> bool {
+    pub fn can_hold(&self, another: &Rectangle<T>) -> bool {
         self.width >= another.width && self.height >= another.height
     }
 
+    /// Like `can_hold`, but `another` may also be turned by 90 degrees to fit.
+    pub fn can_hold_rotated(&self, another: &Rectangle<T>) -> bool {
+        self.can_hold(another) || self.can_hold(&another.rotated())
+    }
+
+    /// The same rectangle turned by 90 degrees.
+    pub fn rotated(&self) -> Rectangle<T> {
+        Rectangle::new(self.height, self.width)
+    }
+
     // associated constructor function (i.e. static factory method)
-    pub fn square(size: u32) -> Self {
+    pub fn square(size: T) -> Self {
         Self {
             width: size,
             height: size,
         }
     }
+
+    /// The same rectangle with its sides converted, for example from `Meters` to `Millimeters`.
+    pub fn convert<U: Dimension + From<T>>(self) -> Rectangle<U> {
+        Rectangle::new(U::from(self.width), U::from(self.height))
+    }
+}
+
+/*
+    Adding two rectangles adds their sides. The sides only need to be addable, not of the same
+    type, so adding a Rectangle<Meters> to a Rectangle<Millimeters> converts the metres, while
+    adding two lengths that don't know each other, like Millimeters and plain u32, doesn't compile.
+ */
+impl<A, B> Add<Rectangle<B>> for Rectangle<A>
+    where
+        A: Dimension + Add<B>,
+        B: Dimension,
+        A::Output: Dimension,
+{
+    type Output = Rectangle<A::Output>;
+
+    fn add(self, other: Rectangle<B>) -> Self::Output {
+        Rectangle::new(self.width + other.width, self.height + other.height)
+    }
 }
 
 #[cfg(test)]
@@ -92,4 +251,53 @@ mod tests {
         let s = Rectangle::new(2, 2);
         assert!(!s.can_hold(&r));
     }
+
+    #[test]
+    fn new_keeps_both_sides() {
+        let r: Rectangle = Rectangle::new(3, 5);
+        assert_eq!((r.width, r.height()), (3, 5));
+        assert_eq!(r.area(), 15);
+    }
+
+    #[test]
+    fn area_of_the_largest_rectangle_does_not_overflow() {
+        let r = Rectangle::square(u32::MAX);
+        assert_eq!(r.area(), 18_446_744_065_119_617_025);
+        assert_eq!(r.checked_area(), Some(r.area()));
+    }
+
+    #[test]
+    fn rotation_lets_a_box_hold_a_turned_item() {
+        let container = Rectangle::new(2, 5);
+        let item = Rectangle::new(5, 2);
+        assert!(!container.can_hold(&item));
+        assert!(container.can_hold_rotated(&item));
+        assert!(!container.can_hold_rotated(&Rectangle::new(3, 3)));
+    }
+
+    #[test]
+    fn sides_can_be_floats_or_signed() {
+        let plank = Rectangle::new(2.5, 0.5);
+        assert_eq!(plank.area(), 1.25);
+        assert!(Rectangle::new(3.0, 3.0).can_hold_rotated(&plank.rotated()));
+        assert_eq!(Rectangle::square(f32::MAX).checked_area(), None);
+
+        let signed: Rectangle<i64> = Rectangle::new(i64::MAX, i64::MAX);
+        assert_eq!(signed.area(), i64::MAX as i128 * i64::MAX as i128);
+        assert_eq!(Rectangle::new(1u8, 2) + Rectangle::new(3, 4), Rectangle::new(4, 6));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn new_with_equal_sides_is_a_square(size: u32) {
+            proptest::prop_assert_eq!(Rectangle::new(size, size), Rectangle::square(size));
+        }
+
+        #[test]
+        fn area_is_the_product_of_the_sides(width: u32, height: u32) {
+            let r = Rectangle::new(width, height);
+            proptest::prop_assert_eq!(r.checked_area(), Some(r.area()));
+            proptest::prop_assert_eq!(r.area() as u128, width as u128 * height as u128);
+        }
+    }
 }
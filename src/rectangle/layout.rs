@@ -0,0 +1,655 @@
+use super::Positioned;
+use crate::json;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// A named rectangle in a layout, like a room of a floor plan or a region of a screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// May be empty.
+    pub name: String,
+    pub bounds: Positioned,
+}
+
+impl Region {
+    pub fn new(name: &str, bounds: Positioned) -> Region {
+        Region { name: String::from(name), bounds }
+    }
+}
+
+/*
+    A set of regions, stored as JSON or CSV.
+
+    JSON is an array of objects. "width" and "height" are required; "name" defaults to "" and
+    "x" and "y" to 0. Other fields are an error, so a typo like "widht" doesn't go unnoticed, and
+    so is a field given twice in one object.
+
+        [
+          {"name": "kitchen", "x": 0, "y": 0, "width": 400, "height": 300},
+          {"name": "hall", "x": 400, "y": 0, "width": 150, "height": 300}
+        ]
+
+    CSV has a header line and one region per line. Names that contain a comma, a quote or a line
+    break are quoted, with quotes doubled:
+
+        name,x,y,width,height
+        kitchen,0,0,400,300
+        "hall, north",400,0,150,300
+
+    Reading checks every value and reports the first problem with the line it is on.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Layout {
+    pub regions: Vec<Region>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// The format for a file name ending in `.json` or `.csv`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+        match path.as_ref().extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(io::Error),
+    /// The file is malformed at `line` (counting from 1).
+    Invalid { line: usize, message: String },
+    UnknownFormat,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Io(error) => write!(f, "I/O error: {error}"),
+            LayoutError::Invalid { line, message } => write!(f, "line {line}: {message}"),
+            LayoutError::UnknownFormat => write!(f, "layout files must end in .json or .csv"),
+        }
+    }
+}
+
+impl Error for LayoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LayoutError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LayoutError {
+    fn from(error: io::Error) -> LayoutError {
+        LayoutError::Io(error)
+    }
+}
+
+fn invalid(line: usize, message: impl Into<String>) -> LayoutError {
+    LayoutError::Invalid { line, message: message.into() }
+}
+
+impl Layout {
+    pub fn new(regions: Vec<Region>) -> Layout {
+        Layout { regions }
+    }
+
+    pub fn read<R: Read>(format: Format, mut input: R) -> Result<Layout, LayoutError> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        match format {
+            Format::Json => Layout::from_json(&text),
+            Format::Csv => Layout::from_csv(&text),
+        }
+    }
+
+    pub fn write<W: Write>(&self, format: Format, output: W) -> io::Result<()> {
+        match format {
+            Format::Json => self.write_json(output),
+            Format::Csv => self.write_csv(output),
+        }
+    }
+
+    /// Reads the layout at `path`, in the format its extension names.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Layout, LayoutError> {
+        let format = Format::from_path(&path).ok_or(LayoutError::UnknownFormat)?;
+        Layout::read(format, fs::File::open(path)?)
+    }
+
+    /// Writes the layout to `path`, in the format its extension names.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), LayoutError> {
+        let format = Format::from_path(&path).ok_or(LayoutError::UnknownFormat)?;
+        let mut output = io::BufWriter::new(fs::File::create(path)?);
+        self.write(format, &mut output)?;
+        output.flush()?;
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, mut output: W) -> io::Result<()> {
+        if self.regions.is_empty() {
+            return writeln!(output, "[]");
+        }
+        writeln!(output, "[")?;
+        for (index, region) in self.regions.iter().enumerate() {
+            let separator = if index + 1 < self.regions.len() { "," } else { "" };
+            let bounds = &region.bounds;
+            let object = json::object(&[
+                ("name", &json::string(&region.name)),
+                ("x", &bounds.origin.x),
+                ("y", &bounds.origin.y),
+                ("width", &bounds.size.width),
+                ("height", &bounds.size.height()),
+            ]);
+            writeln!(output, "  {object}{separator}")?;
+        }
+        writeln!(output, "]")
+    }
+
+    pub fn write_csv<W: Write>(&self, mut output: W) -> io::Result<()> {
+        writeln!(output, "{CSV_HEADER}")?;
+        for region in &self.regions {
+            writeln!(
+                output,
+                "{},{},{},{},{}",
+                csv_field(&region.name),
+                region.bounds.origin.x,
+                region.bounds.origin.y,
+                region.bounds.size.width,
+                region.bounds.size.height()
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn from_json(text: &str) -> Result<Layout, LayoutError> {
+        let mut parser = JsonParser { chars: text.chars().peekable(), line: 1 };
+        let document = parser.value()?;
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() {
+            return Err(invalid(parser.line, "unexpected text after the layout"));
+        }
+
+        let Json::Array(items) = document.json else {
+            return Err(invalid(document.line, "a layout must be an array of regions"));
+        };
+        let regions = items.into_iter().map(region_from_json).collect::<Result<_, _>>()?;
+        Ok(Layout { regions })
+    }
+
+    pub fn from_csv(text: &str) -> Result<Layout, LayoutError> {
+        let mut records = csv_records(text)?.into_iter();
+        match records.next() {
+            Some((_, header)) if header.join(",") == CSV_HEADER => {}
+            Some((line, _)) => return Err(invalid(line, format!("the header must be `{CSV_HEADER}`"))),
+            None => return Err(invalid(1, format!("missing the header `{CSV_HEADER}`"))),
+        }
+
+        let mut regions = vec![];
+        for (line, fields) in records {
+            let [name, x, y, width, height] = <[String; 5]>::try_from(fields)
+                .map_err(|fields| invalid(line, format!("expected 5 fields, found {}", fields.len())))?;
+            let bounds = Positioned::new(
+                number(line, "x", &x)?,
+                number(line, "y", &y)?,
+                number(line, "width", &width)?,
+                number(line, "height", &height)?,
+            );
+            regions.push(Region { name, bounds });
+        }
+        Ok(Layout { regions })
+    }
+}
+
+// Parses a whole number of type T, with a message that names the field and its allowed range.
+fn number<T>(line: usize, field: &str, text: &str) -> Result<T, LayoutError>
+    where
+        T: std::str::FromStr + Bounded,
+{
+    text.trim().parse().map_err(|_| {
+        invalid(
+            line,
+            format!("{field} must be a whole number from {} to {}, got `{text}`", T::MIN, T::MAX),
+        )
+    })
+}
+
+trait Bounded: fmt::Display {
+    const MIN: Self;
+    const MAX: Self;
+}
+
+impl Bounded for i32 {
+    const MIN: i32 = i32::MIN;
+    const MAX: i32 = i32::MAX;
+}
+
+impl Bounded for u32 {
+    const MIN: u32 = u32::MIN;
+    const MAX: u32 = u32::MAX;
+}
+
+const CSV_HEADER: &str = "name,x,y,width,height";
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) || text.trim() != text {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        String::from(text)
+    }
+}
+
+// Splits CSV text into records, each with the line it starts on; blank lines are skipped.
+fn csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, LayoutError> {
+    let mut records = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+
+        loop {
+            match chars.next() {
+                Some('"') if field.is_empty() && !quoted => {
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                field.push(c);
+                            }
+                            None => return Err(invalid(start, "a quoted field is never closed")),
+                        }
+                    }
+                    if !matches!(chars.peek(), Some(',' | '\n' | '\r') | None) {
+                        return Err(invalid(line, "a quoted field must end at a comma or the end of the line"));
+                    }
+                }
+                Some(',') => {
+                    fields.push(std::mem::take(&mut field));
+                    quoted = false;
+                }
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') | None => {
+                    line += 1;
+                    fields.push(field);
+                    break;
+                }
+                Some(c) => field.push(c),
+            }
+        }
+
+        if !(fields.len() == 1 && fields[0].is_empty() && !quoted) {
+            records.push((start, fields));
+        }
+    }
+    Ok(records)
+}
+
+// A JSON value and the line it starts on.
+struct Value {
+    line: usize,
+    json: Json,
+}
+
+enum Json {
+    Null,
+    Bool,
+    // Kept as written, so whole numbers can be parsed without going through a float.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Json {
+    fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool => "a boolean",
+            Json::Number(_) => "a number",
+            Json::String(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+}
+
+fn region_from_json(value: Value) -> Result<Region, LayoutError> {
+    let Json::Object(fields) = value.json else {
+        return Err(invalid(value.line, format!("a region must be an object, not {}", value.json.kind())));
+    };
+
+    let mut name = String::new();
+    let (mut x, mut y, mut width, mut height) = (0, 0, None, None);
+    for (key, value) in fields {
+        let line = value.line;
+        let whole = |value: &Json| match value {
+            Json::Number(text) => Ok(text.clone()),
+            other => Err(invalid(line, format!("{key} must be a number, not {}", other.kind()))),
+        };
+        match key.as_str() {
+            "name" => match value.json {
+                Json::String(text) => name = text,
+                other => return Err(invalid(line, format!("name must be a string, not {}", other.kind()))),
+            },
+            "x" => x = number(line, "x", &whole(&value.json)?)?,
+            "y" => y = number(line, "y", &whole(&value.json)?)?,
+            "width" => width = Some(number(line, "width", &whole(&value.json)?)?),
+            "height" => height = Some(number(line, "height", &whole(&value.json)?)?),
+            _ => return Err(invalid(line, format!("unknown field `{key}`"))),
+        }
+    }
+
+    let width = width.ok_or_else(|| invalid(value.line, "a region needs a width"))?;
+    let height = height.ok_or_else(|| invalid(value.line, "a region needs a height"))?;
+    Ok(Region { name, bounds: Positioned::new(x, y, width, height) })
+}
+
+// Just enough of a JSON parser for layout files, counting lines as it goes.
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl JsonParser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), LayoutError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(invalid(self.line, format!("expected `{expected}`, found `{c}`"))),
+            None => Err(invalid(self.line, format!("expected `{expected}`, found the end of the file"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, LayoutError> {
+        self.skip_whitespace();
+        let line = self.line;
+        let json = match self.chars.peek().copied() {
+            Some('[') => {
+                self.next();
+                let mut items = vec![];
+                if !self.close(']') {
+                    loop {
+                        items.push(self.value()?);
+                        if self.separator(']')? {
+                            break;
+                        }
+                    }
+                }
+                Json::Array(items)
+            }
+            Some('{') => {
+                self.next();
+                let mut fields = vec![];
+                if !self.close('}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key_line = self.line;
+                        let key = match self.chars.peek() {
+                            Some('"') => self.string()?,
+                            _ => return Err(invalid(self.line, "expected a field name in quotes")),
+                        };
+                        if fields.iter().any(|(other, _)| *other == key) {
+                            return Err(invalid(key_line, format!("duplicate field `{key}`")));
+                        }
+                        self.expect(':')?;
+                        fields.push((key, self.value()?));
+                        if self.separator('}')? {
+                            break;
+                        }
+                    }
+                }
+                Json::Object(fields)
+            }
+            Some('"') => Json::String(self.string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut text = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                        break;
+                    }
+                    text.push(c);
+                    self.next();
+                }
+                Json::Number(text)
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    word.push(c);
+                    self.next();
+                }
+                match word.as_str() {
+                    "null" => Json::Null,
+                    "true" | "false" => Json::Bool,
+                    _ => return Err(invalid(line, format!("unexpected `{word}`"))),
+                }
+            }
+            Some(c) => return Err(invalid(line, format!("unexpected `{c}`"))),
+            None => return Err(invalid(line, "unexpected end of the file")),
+        };
+        Ok(Value { line, json })
+    }
+
+    // Consumes `close` if it comes next, for empty arrays and objects.
+    fn close(&mut self, close: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&close) {
+            self.next();
+            return true;
+        }
+        false
+    }
+
+    // After an item: true at the closing bracket, false after a comma.
+    fn separator(&mut self, close: char) -> Result<bool, LayoutError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(',') => Ok(false),
+            Some(c) if c == close => Ok(true),
+            Some(c) => Err(invalid(self.line, format!("expected `,` or `{close}`, found `{c}`"))),
+            None => Err(invalid(self.line, format!("expected `,` or `{close}`, found the end of the file"))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, LayoutError> {
+        let start = self.line;
+        self.next();
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => match self.next() {
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('/') => text.push('/'),
+                    Some('b') => text.push('\u{8}'),
+                    Some('f') => text.push('\u{c}'),
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('u') => text.push(self.unicode_escape()?),
+                    _ => return Err(invalid(self.line, "unknown escape in a string")),
+                },
+                Some('\n') => return Err(invalid(start, "a string can't contain a line break")),
+                Some(c) => text.push(c),
+                None => return Err(invalid(start, "a string is never closed")),
+            }
+        }
+    }
+
+    // The character of a \uXXXX escape, which takes two escapes outside the basic plane.
+    fn unicode_escape(&mut self) -> Result<char, LayoutError> {
+        let hex = |parser: &mut Self| -> Result<u32, LayoutError> {
+            let digits: String = (0..4).filter_map(|_| parser.next()).collect();
+            u32::from_str_radix(&digits, 16).map_err(|_| invalid(parser.line, "bad \\u escape in a string"))
+        };
+
+        let first = hex(self)?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err(invalid(self.line, "unpaired surrogate in a string"));
+            }
+            let second = hex(self)?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err(invalid(self.line, "unpaired surrogate in a string"));
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| invalid(self.line, "bad \\u escape in a string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn floor_plan() -> Layout {
+        Layout::new(vec![
+            Region::new("kitchen", Positioned::new(0, 0, 400, 300)),
+            Region::new("hall, \"north\"", Positioned::new(400, -20, 150, 300)),
+            Region::new("", Positioned::new(i32::MIN, i32::MAX, u32::MAX, 0)),
+        ])
+    }
+
+    fn written(layout: &Layout, format: Format) -> String {
+        let mut output = vec![];
+        layout.write(format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn error_line(result: Result<Layout, LayoutError>) -> (usize, String) {
+        match result {
+            Err(LayoutError::Invalid { line, message }) => (line, message),
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn writes_both_formats() {
+        assert_eq!(
+            written(&floor_plan(), Format::Csv),
+            "name,x,y,width,height\n\
+             kitchen,0,0,400,300\n\
+             \"hall, \"\"north\"\"\",400,-20,150,300\n\
+             ,-2147483648,2147483647,4294967295,0\n"
+        );
+        assert!(written(&floor_plan(), Format::Json).starts_with(
+            "[\n  {\"name\": \"kitchen\", \"x\": 0, \"y\": 0, \"width\": 400, \"height\": 300},\n"
+        ));
+        assert_eq!(written(&Layout::default(), Format::Json), "[]\n");
+    }
+
+    #[test]
+    fn round_trips_through_both_formats() {
+        for format in [Format::Json, Format::Csv] {
+            let layout = floor_plan();
+            let text = written(&layout, format);
+            assert_eq!(Layout::read(format, text.as_bytes()).unwrap(), layout, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn json_defaults_and_escapes() {
+        let text = r#"[{"width": 2, "height": 3}, {"name": "café 😀\n", "x": -1, "width": 1, "height": 1}]"#;
+        let layout = Layout::from_json(text).unwrap();
+
+        assert_eq!(layout.regions[0], Region::new("", Positioned::new(0, 0, 2, 3)));
+        assert_eq!(layout.regions[1].name, "café 😀\n");
+    }
+
+    #[test]
+    fn reports_json_errors_with_their_line() {
+        let bad_width = "[\n  {\"width\": 1, \"height\": 1},\n  {\"width\": -4, \"height\": 1}\n]";
+        let (line, message) = error_line(Layout::from_json(bad_width));
+        assert_eq!(line, 3);
+        assert_eq!(message, "width must be a whole number from 0 to 4294967295, got `-4`");
+
+        assert_eq!(error_line(Layout::from_json("[\n{\"width\": 1.5, \"height\": 1}]")).0, 2);
+        assert_eq!(error_line(Layout::from_json("[\n\n{\"height\": 1}]")), (3, String::from("a region needs a width")));
+        assert_eq!(error_line(Layout::from_json("[{\"width\": 1, \"height\": 1,\n\"widht\": 2}]")).0, 2);
+        assert_eq!(error_line(Layout::from_json("[\n{\"width\": 1 \"height\": 1}]")).0, 2);
+        assert_eq!(error_line(Layout::from_json("{}")).1, "a layout must be an array of regions");
+        assert_eq!(error_line(Layout::from_json("[]\n\n[]")).0, 3);
+        assert_eq!(
+            error_line(Layout::from_json("[{\"width\": 1, \"height\": 1,\n\n  \"width\": 2}]")),
+            (3, String::from("duplicate field `width`"))
+        );
+    }
+
+    #[test]
+    fn reports_csv_errors_with_their_line() {
+        let (line, message) = error_line(Layout::from_csv("name,x,y,width,height\na,0,0,1,1\n\nb,0,zero,1,1\n"));
+        assert_eq!(line, 4);
+        assert_eq!(message, "y must be a whole number from -2147483648 to 2147483647, got `zero`");
+
+        // A quoted line break belongs to the record, so the next record starts a line later.
+        let text = "name,x,y,width,height\n\"two\nlines\",0,0,1,1\nc,0,0,1\n";
+        assert_eq!(error_line(Layout::from_csv(text)), (4, String::from("expected 5 fields, found 4")));
+
+        assert_eq!(error_line(Layout::from_csv("width,height\n1,1\n")).0, 1);
+        assert_eq!(error_line(Layout::from_csv("name,x,y,width,height\n\"open,0,0,1,1\n")).0, 2);
+        assert_eq!(Layout::from_csv("name,x,y,width,height\r\na,1,2,3,4\r\n").unwrap().regions.len(), 1);
+    }
+
+    #[test]
+    fn picks_the_format_from_the_extension() {
+        assert_eq!(Format::from_path("plan.JSON"), Some(Format::Json));
+        assert_eq!(Format::from_path("regions.csv"), Some(Format::Csv));
+        assert!(matches!(Layout::load("plan.txt"), Err(LayoutError::UnknownFormat)));
+    }
+
+    fn region() -> impl Strategy<Value = Region> {
+        (".*", any::<i32>(), any::<i32>(), any::<u32>(), any::<u32>())
+            .prop_map(|(name, x, y, width, height)| Region { name, bounds: Positioned::new(x, y, width, height) })
+    }
+
+    proptest! {
+        #[test]
+        fn any_layout_round_trips(regions in prop::collection::vec(region(), 0..8)) {
+            let layout = Layout::new(regions);
+            for format in [Format::Json, Format::Csv] {
+                let text = written(&layout, format);
+                prop_assert_eq!(&Layout::read(format, text.as_bytes()).unwrap(), &layout);
+            }
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use super::{Positioned, Rectangle};
+use std::fmt;
+
+/*
+    Two-dimensional bin packing: place item rectangles inside container rectangles without any
+    two items overlapping. Finding the best packing is NP-hard, so like everybody else we use
+    heuristics, which place the items one at a time, largest first, and never move them again.
+
+    Shelf        fills the container in rows ("shelves") as tall as the first item placed in them.
+                 Fast and simple, but wastes the space above the shorter items of a shelf.
+    Guillotine   keeps a list of free rectangles. Placing an item in one splits what is left of
+                 it into two smaller free rectangles, as if cutting it with a guillotine.
+    MaxRects     keeps every maximal free rectangle, even when they overlap, so no free space is
+                 ever lost to an unlucky cut. Usually the tightest, and the slowest of the three.
+
+    Every heuristic decides whether an item fits with Rectangle::can_hold, trying the item both
+    ways round when rotation is allowed. Containers are filled together, so a small item can
+    still go into an earlier container after a later one was opened.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Heuristic {
+    Shelf,
+    Guillotine,
+    MaxRects,
+}
+
+impl Heuristic {
+    pub const ALL: [Heuristic; 3] = [Heuristic::Shelf, Heuristic::Guillotine, Heuristic::MaxRects];
+}
+
+impl fmt::Display for Heuristic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Heuristic::Shelf => write!(f, "shelf"),
+            Heuristic::Guillotine => write!(f, "guillotine"),
+            Heuristic::MaxRects => write!(f, "maxrects"),
+        }
+    }
+}
+
+/// Where one item ended up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// Index into the items passed to `pack`.
+    pub item: usize,
+    /// Index into the containers passed to `pack`.
+    pub container: usize,
+    /// Relative to the container's top-left corner, with the item's size after any rotation.
+    pub position: Positioned,
+    pub rotated: bool,
+}
+
+/// How full one container is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utilisation {
+    pub container: usize,
+    pub items: usize,
+    pub used_area: u64,
+    pub area: u64,
+}
+
+impl Utilisation {
+    /// The used fraction of the container's area, from 0 to 1.
+    pub fn ratio(&self) -> f64 {
+        if self.area == 0 {
+            0.0
+        } else {
+            self.used_area as f64 / self.area as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packing {
+    pub placements: Vec<Placement>,
+    /// Items that fit in no container, as indices into the items passed to `pack`.
+    pub unplaced: Vec<usize>,
+    pub containers: Vec<Rectangle>,
+}
+
+impl Packing {
+    pub fn utilisation(&self) -> Vec<Utilisation> {
+        let mut report: Vec<Utilisation> = self
+            .containers
+            .iter()
+            .enumerate()
+            .map(|(container, size)| Utilisation { container, items: 0, used_area: 0, area: size.area() })
+            .collect();
+        for placement in &self.placements {
+            let entry = &mut report[placement.container];
+            entry.items += 1;
+            entry.used_area += placement.position.size.area();
+        }
+        report
+    }
+
+    /// The used fraction of the area of all the containers that hold at least one item.
+    pub fn total_utilisation(&self) -> f64 {
+        let (used, area) = self
+            .utilisation()
+            .iter()
+            .filter(|entry| entry.items > 0)
+            .fold((0, 0), |(used, area), entry| (used + entry.used_area, area + entry.area));
+        if area == 0 {
+            0.0
+        } else {
+            used as f64 / area as f64
+        }
+    }
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.utilisation() {
+            let size = self.containers[entry.container];
+            writeln!(
+                f,
+                "container {} ({}x{}): {} items, {:.1}% used",
+                entry.container,
+                size.width,
+                size.height(),
+                entry.items,
+                entry.ratio() * 100.0
+            )?;
+        }
+        writeln!(f, "{} items did not fit", self.unplaced.len())
+    }
+}
+
+// The free space of one container, as each heuristic sees it.
+trait Bin {
+    /// Places `item` if it fits and returns where, with its size after any rotation.
+    fn insert(&mut self, item: Rectangle, allow_rotation: bool) -> Option<Positioned>;
+}
+
+// The orientations worth trying for `item`.
+fn orientations(item: Rectangle, allow_rotation: bool) -> Vec<Rectangle> {
+    if allow_rotation && item.width != item.height() {
+        vec![item, item.rotated()]
+    } else {
+        vec![item]
+    }
+}
+
+fn at(x: i64, y: i64, size: Rectangle) -> Positioned {
+    // pack only accepts containers whose sides fit in an i32, so every offset inside them does.
+    Positioned::new(x as i32, y as i32, size.width, size.height())
+}
+
+struct Shelf {
+    top: i64,
+    height: u32,
+    used_width: u32,
+}
+
+struct ShelfBin {
+    size: Rectangle,
+    shelves: Vec<Shelf>,
+}
+
+impl Bin for ShelfBin {
+    fn insert(&mut self, item: Rectangle, allow_rotation: bool) -> Option<Positioned> {
+        // On the first shelf with room, take the orientation that wastes the least height.
+        for shelf in &mut self.shelves {
+            let free = Rectangle::new(self.size.width - shelf.used_width, shelf.height);
+            let fit = orientations(item, allow_rotation)
+                .into_iter()
+                .filter(|candidate| free.can_hold(candidate))
+                .max_by_key(|candidate| candidate.height());
+            if let Some(size) = fit {
+                let position = at(shelf.used_width as i64, shelf.top, size);
+                shelf.used_width += size.width;
+                return Some(position);
+            }
+        }
+
+        // Otherwise open a new shelf, as low as possible so it leaves room for more shelves.
+        let top = self.shelves.last().map_or(0, |shelf| shelf.top + shelf.height as i64);
+        let free = Rectangle::new(self.size.width, self.size.height() - top as u32);
+        let size = orientations(item, allow_rotation)
+            .into_iter()
+            .filter(|candidate| free.can_hold(candidate))
+            .min_by_key(|candidate| candidate.height())?;
+        self.shelves.push(Shelf { top, height: size.height(), used_width: size.width });
+        Some(at(0, top, size))
+    }
+}
+
+struct GuillotineBin {
+    free: Vec<Positioned>,
+}
+
+impl Bin for GuillotineBin {
+    fn insert(&mut self, item: Rectangle, allow_rotation: bool) -> Option<Positioned> {
+        // Best area fit: the free rectangle that is left with the least area.
+        let (index, size) = self
+            .free
+            .iter()
+            .enumerate()
+            .flat_map(|(index, free)| {
+                orientations(item, allow_rotation)
+                    .into_iter()
+                    .filter(|candidate| free.size.can_hold(candidate))
+                    .map(move |candidate| (index, candidate))
+            })
+            .min_by_key(|(index, candidate)| self.free[*index].size.area() - candidate.area())?;
+
+        let free = self.free.swap_remove(index);
+        let placed = at(free.left(), free.top(), size);
+
+        // Cut along the shorter leftover axis, which keeps the bigger leftover piece in one part.
+        let right_width = free.size.width - size.width;
+        let bottom_height = free.size.height() - size.height();
+        let (right, bottom) = if right_width < bottom_height {
+            (
+                at(placed.right(), free.top(), Rectangle::new(right_width, size.height())),
+                at(free.left(), placed.bottom(), Rectangle::new(free.size.width, bottom_height)),
+            )
+        } else {
+            (
+                at(placed.right(), free.top(), Rectangle::new(right_width, free.size.height())),
+                at(free.left(), placed.bottom(), Rectangle::new(size.width, bottom_height)),
+            )
+        };
+        self.free.extend([right, bottom].into_iter().filter(|piece| !piece.is_empty()));
+        Some(placed)
+    }
+}
+
+struct MaxRectsBin {
+    free: Vec<Positioned>,
+}
+
+impl Bin for MaxRectsBin {
+    fn insert(&mut self, item: Rectangle, allow_rotation: bool) -> Option<Positioned> {
+        // Best short side fit: the free rectangle where the item leaves the thinnest strip.
+        let placed = self
+            .free
+            .iter()
+            .flat_map(|free| {
+                orientations(item, allow_rotation)
+                    .into_iter()
+                    .filter(|candidate| free.size.can_hold(candidate))
+                    .map(move |candidate| (free, candidate))
+            })
+            .min_by_key(|(free, candidate)| {
+                let leftover_width = free.size.width - candidate.width;
+                let leftover_height = free.size.height() - candidate.height();
+                (leftover_width.min(leftover_height), leftover_width.max(leftover_height))
+            })
+            .map(|(free, candidate)| at(free.left(), free.top(), candidate))?;
+
+        // Every free rectangle the item overlaps is replaced by the (up to four) maximal
+        // rectangles around the item that are still free.
+        let mut free = vec![];
+        for rect in self.free.drain(..) {
+            if !rect.intersects(&placed) {
+                free.push(rect);
+                continue;
+            }
+            let (width, height) = (rect.size.width as i64, rect.size.height() as i64);
+            let pieces = [
+                (rect.left(), rect.top(), placed.left() - rect.left(), height),
+                (placed.right(), rect.top(), rect.right() - placed.right(), height),
+                (rect.left(), rect.top(), width, placed.top() - rect.top()),
+                (rect.left(), placed.bottom(), width, rect.bottom() - placed.bottom()),
+            ];
+            for (x, y, piece_width, piece_height) in pieces {
+                if piece_width > 0 && piece_height > 0 {
+                    free.push(at(x, y, Rectangle::new(piece_width as u32, piece_height as u32)));
+                }
+            }
+        }
+
+        // Drop free rectangles that lie inside another one; only the maximal ones are kept.
+        let mut maximal: Vec<Positioned> = vec![];
+        for (index, rect) in free.iter().enumerate() {
+            let redundant = free.iter().enumerate().any(|(other_index, other)| {
+                other_index != index && other.contains_rect(rect) && (other != rect || other_index < index)
+            });
+            if !redundant {
+                maximal.push(*rect);
+            }
+        }
+        self.free = maximal;
+        Some(placed)
+    }
+}
+
+fn new_bin(heuristic: Heuristic, size: Rectangle) -> Box<dyn Bin> {
+    let whole = Positioned::new(0, 0, size.width, size.height());
+    match heuristic {
+        Heuristic::Shelf => Box::new(ShelfBin { size, shelves: vec![] }),
+        Heuristic::Guillotine => Box::new(GuillotineBin { free: vec![whole] }),
+        Heuristic::MaxRects => Box::new(MaxRectsBin { free: vec![whole] }),
+    }
+}
+
+/// Packs `items` into `containers` with `heuristic`, turning items by 90 degrees if `allow_rotation`.
+///
+/// # Panics
+///
+/// If a container side is larger than `i32::MAX`, because placements are `Positioned` rectangles.
+pub fn pack(items: &[Rectangle], containers: &[Rectangle], heuristic: Heuristic, allow_rotation: bool) -> Packing {
+    for container in containers {
+        assert!(
+            container.width <= i32::MAX as u32 && container.height() <= i32::MAX as u32,
+            "container {container:?} is too large to place items in"
+        );
+    }
+
+    // Big items first: they are the hard ones to fit, and small ones fill the gaps they leave.
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by_key(|&index| {
+        let item = items[index];
+        let key = match heuristic {
+            // With rotation, shelf items are laid flat, so their shorter side becomes their height.
+            Heuristic::Shelf if allow_rotation => (item.width.min(item.height()), item.area()),
+            Heuristic::Shelf => (item.height(), item.area()),
+            Heuristic::Guillotine | Heuristic::MaxRects => (item.width.max(item.height()), item.area()),
+        };
+        std::cmp::Reverse(key)
+    });
+
+    let mut bins: Vec<Box<dyn Bin>> = containers.iter().map(|size| new_bin(heuristic, *size)).collect();
+    let mut placements = vec![];
+    let mut unplaced = vec![];
+
+    for item in order {
+        let size = items[item];
+        let placed = bins
+            .iter_mut()
+            .enumerate()
+            .find_map(|(container, bin)| Some((container, bin.insert(size, allow_rotation)?)));
+        match placed {
+            Some((container, position)) => placements.push(Placement {
+                item,
+                container,
+                position,
+                rotated: position.size != size,
+            }),
+            None => unplaced.push(item),
+        }
+    }
+    unplaced.sort();
+
+    Packing { placements, unplaced, containers: containers.to_vec() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Every placed item lies inside its container, keeps its size and overlaps no other item.
+    fn assert_valid(packing: &Packing, items: &[Rectangle]) {
+        for (index, placement) in packing.placements.iter().enumerate() {
+            let container = packing.containers[placement.container];
+            let bounds = Positioned::new(0, 0, container.width, container.height());
+            let item = items[placement.item];
+            let expected = if placement.rotated { item.rotated() } else { item };
+
+            assert_eq!(placement.position.size, expected);
+            assert!(bounds.contains_rect(&placement.position), "{placement:?} is outside {container:?}");
+            for other in &packing.placements[index + 1..] {
+                let overlap = other.container == placement.container && other.position.intersects(&placement.position);
+                assert!(!overlap, "{placement:?} overlaps {other:?}");
+            }
+        }
+        assert_eq!(packing.placements.len() + packing.unplaced.len(), items.len());
+    }
+
+    #[test]
+    fn a_tall_box_holds_a_wide_item_only_with_rotation() {
+        let items = [Rectangle::new(5, 2)];
+        let containers = [Rectangle::new(2, 5)];
+
+        for heuristic in Heuristic::ALL {
+            assert_eq!(pack(&items, &containers, heuristic, false).unplaced, vec![0]);
+
+            let packing = pack(&items, &containers, heuristic, true);
+            assert_valid(&packing, &items);
+            assert!(packing.placements[0].rotated, "{heuristic}");
+            assert_eq!(packing.utilisation()[0].ratio(), 1.0);
+        }
+    }
+
+    #[test]
+    fn fills_a_container_exactly() {
+        // Four 5x5 squares tile a 10x10 container.
+        let items = [Rectangle::square(5); 4];
+        let containers = [Rectangle::square(10), Rectangle::square(10)];
+
+        for heuristic in Heuristic::ALL {
+            let packing = pack(&items, &containers, heuristic, false);
+            assert_valid(&packing, &items);
+            let report = packing.utilisation();
+            assert_eq!((report[0].items, report[1].items), (4, 0), "{heuristic}");
+            assert_eq!(packing.total_utilisation(), 1.0);
+        }
+    }
+
+    #[test]
+    fn overflows_into_the_next_container() {
+        let items = [Rectangle::new(6, 6), Rectangle::new(6, 6), Rectangle::new(20, 1)];
+        let containers = [Rectangle::square(10), Rectangle::square(10)];
+
+        for heuristic in Heuristic::ALL {
+            let packing = pack(&items, &containers, heuristic, true);
+            assert_valid(&packing, &items);
+            assert_eq!(packing.unplaced, vec![2]);
+            assert_eq!(packing.utilisation()[1].items, 1);
+            assert!(packing.to_string().ends_with("1 items did not fit\n"));
+        }
+    }
+
+    fn rectangles(max_side: u32, max_count: usize) -> impl Strategy<Value = Vec<Rectangle>> {
+        prop::collection::vec((1..=max_side, 1..=max_side).prop_map(|(w, h)| Rectangle::new(w, h)), 0..max_count)
+    }
+
+    proptest! {
+        #[test]
+        fn packings_are_always_valid(
+            items in rectangles(20, 40),
+            containers in rectangles(50, 4),
+            allow_rotation: bool,
+        ) {
+            for heuristic in Heuristic::ALL {
+                assert_valid(&pack(&items, &containers, heuristic, allow_rotation), &items);
+            }
+        }
+    }
+}
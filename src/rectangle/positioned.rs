@@ -0,0 +1,185 @@
+use super::Rectangle;
+
+/// A point on the plane; y grows downwards, like rows on a screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+}
+
+/*
+    A Rectangle placed on the plane with its top-left corner at `origin`.
+
+    Rectangles are half-open: one covers the points with origin.x <= x < origin.x + width and
+    origin.y <= y < origin.y + height. That way two rectangles that only share an edge don't
+    intersect, and a row of rectangles placed side by side covers every point exactly once.
+    Coordinates are i32 and sides u32, so the far edges are computed in i64 where they can't
+    overflow.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Positioned {
+    pub origin: Point,
+    pub size: Rectangle,
+}
+
+impl Positioned {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Positioned {
+        Positioned {
+            origin: Point::new(x, y),
+            size: Rectangle::new(width, height),
+        }
+    }
+
+    pub fn left(&self) -> i64 {
+        self.origin.x as i64
+    }
+
+    pub fn top(&self) -> i64 {
+        self.origin.y as i64
+    }
+
+    /// The first column to the right of the rectangle.
+    pub fn right(&self) -> i64 {
+        self.left() + self.size.width as i64
+    }
+
+    /// The first row below the rectangle.
+    pub fn bottom(&self) -> i64 {
+        self.top() + self.size.height() as i64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.area() == 0
+    }
+
+    pub fn contains_point(&self, point: Point) -> bool {
+        let (x, y) = (point.x as i64, point.y as i64);
+        self.left() <= x && x < self.right() && self.top() <= y && y < self.bottom()
+    }
+
+    /// Whether every point of `other` is also in `self`; an empty `other` must still lie within.
+    pub fn contains_rect(&self, other: &Positioned) -> bool {
+        self.left() <= other.left()
+            && other.right() <= self.right()
+            && self.top() <= other.top()
+            && other.bottom() <= self.bottom()
+    }
+
+    /// Whether the two rectangles share at least one point; touching edges don't count.
+    pub fn intersects(&self, other: &Positioned) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// The points both rectangles cover, or `None` if there are none.
+    pub fn intersection(&self, other: &Positioned) -> Option<Positioned> {
+        let (left, top) = (self.left().max(other.left()), self.top().max(other.top()));
+        let (right, bottom) = (self.right().min(other.right()), self.bottom().min(other.bottom()));
+        if left >= right || top >= bottom {
+            return None;
+        }
+        // Both edges come from the inputs, so the result fits wherever they did.
+        Some(Positioned::new(left as i32, top as i32, (right - left) as u32, (bottom - top) as u32))
+    }
+
+    /// The squared distance from `point` to the nearest point of the rectangle: 0 inside it, `None`
+    /// for an empty rectangle, which has no points.
+    pub fn distance_squared(&self, point: Point) -> Option<u128> {
+        if self.is_empty() {
+            return None;
+        }
+        let axis = |value: i64, low: i64, high: i64| {
+            if value < low {
+                low - value
+            } else if value >= high {
+                value - (high - 1)
+            } else {
+                0
+            }
+        };
+        let dx = axis(point.x as i64, self.left(), self.right()) as u128;
+        let dy = axis(point.y as i64, self.top(), self.bottom()) as u128;
+        Some(dx * dx + dy * dy)
+    }
+
+    /*
+        The smallest rectangle that covers both, also called their bounding box. Two rectangles
+        far apart can need a side longer than u32::MAX, so this is `None` when the box doesn't fit.
+     */
+    pub fn union(&self, other: &Positioned) -> Option<Positioned> {
+        let (left, top) = (self.left().min(other.left()), self.top().min(other.top()));
+        let (right, bottom) = (self.right().max(other.right()), self.bottom().max(other.bottom()));
+        Some(Positioned::new(
+            left as i32,
+            top as i32,
+            u32::try_from(right - left).ok()?,
+            u32::try_from(bottom - top).ok()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn touching_rectangles_do_not_intersect() {
+        let a = Positioned::new(0, 0, 2, 2);
+        let b = Positioned::new(2, 0, 2, 2);
+
+        assert!(!a.intersects(&b));
+        assert_eq!(a.union(&b), Some(Positioned::new(0, 0, 4, 2)));
+        assert!(a.contains_point(Point::new(1, 1)));
+        assert!(!a.contains_point(Point::new(2, 1)));
+        assert_eq!(a.distance_squared(Point::new(1, 1)), Some(0));
+        assert_eq!(a.distance_squared(Point::new(4, -2)), Some(9 + 4));
+        assert_eq!(Positioned::new(0, 0, 0, 3).distance_squared(Point::new(0, 0)), None);
+    }
+
+    #[test]
+    fn overlapping_rectangles() {
+        let a = Positioned::new(-2, -2, 5, 5);
+        let b = Positioned::new(1, 0, 4, 1);
+
+        assert_eq!(a.intersection(&b), Some(Positioned::new(1, 0, 2, 1)));
+        assert!(!a.contains_rect(&b));
+        assert!(a.contains_rect(&a.intersection(&b).unwrap()));
+        assert!(a.union(&b).unwrap().contains_rect(&b));
+    }
+
+    #[test]
+    fn the_bounding_box_of_opposite_corners_is_too_wide() {
+        let a = Positioned::new(i32::MIN, 0, u32::MAX, 1);
+        let b = Positioned::new(i32::MAX, 0, u32::MAX, 1);
+
+        assert_eq!(a.union(&b), None);
+        assert_eq!(a.intersection(&b), None);
+    }
+
+    fn positioned() -> impl Strategy<Value = Positioned> {
+        (-50..50i32, -50..50i32, 0..40u32, 0..40u32).prop_map(|(x, y, w, h)| Positioned::new(x, y, w, h))
+    }
+
+    proptest! {
+        #[test]
+        fn intersection_is_the_points_in_both(a in positioned(), b in positioned(), x in -60..100i32, y in -60..100i32) {
+            let point = Point::new(x, y);
+            let in_both = a.contains_point(point) && b.contains_point(point);
+            let in_intersection = a.intersection(&b).is_some_and(|i| i.contains_point(point));
+            prop_assert_eq!(in_both, in_intersection);
+        }
+
+        #[test]
+        fn union_contains_both(a in positioned(), b in positioned()) {
+            let union = a.union(&b).unwrap();
+            prop_assert!(union.contains_rect(&a) && union.contains_rect(&b));
+            prop_assert_eq!(a.intersects(&b), b.intersects(&a));
+        }
+    }
+}
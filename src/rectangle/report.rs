@@ -0,0 +1,310 @@
+use super::{Dimension, Rectangle};
+use crate::json;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+/// What to sort rectangles by, smallest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Order {
+    Area,
+    Perimeter,
+    /// Width divided by height, so tall rectangles come before wide ones.
+    AspectRatio,
+    Width,
+}
+
+fn compare<T: Dimension>(a: &Rectangle<T>, b: &Rectangle<T>, order: Order) -> Ordering {
+    match order {
+        Order::Area => a.area().partial_cmp(&b.area()),
+        Order::Perimeter => a.perimeter().partial_cmp(&b.perimeter()),
+        Order::AspectRatio => Some(a.aspect_ratio().total_cmp(&b.aspect_ratio())),
+        Order::Width => a.width.partial_cmp(&b.width),
+    }
+    // Only NaN float sides are unordered; they stay where they are.
+    .unwrap_or(Ordering::Equal)
+}
+
+/// Sorts `rectangles` by `order`; the sort is stable, so equal rectangles keep their order.
+pub fn sort_by<T: Dimension>(rectangles: &mut [Rectangle<T>], order: Order) {
+    rectangles.sort_by(|a, b| compare(a, b, order));
+}
+
+/// Counts, total area and extremes of a set of rectangles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary<T: Dimension> {
+    pub count: usize,
+    /// Stops at the largest value of the area type rather than overflowing.
+    pub total_area: T::Area,
+    /// The first of the rectangles with the smallest area.
+    pub smallest: Option<Rectangle<T>>,
+    /// The first of the rectangles with the largest area.
+    pub largest: Option<Rectangle<T>>,
+}
+
+impl<T: Dimension> Summary<T> {
+    pub fn of(rectangles: &[Rectangle<T>]) -> Summary<T> {
+        let mut summary = Summary {
+            count: 0,
+            total_area: T::Area::default(),
+            smallest: None,
+            largest: None,
+        };
+        for rectangle in rectangles {
+            summary.add(*rectangle);
+        }
+        summary
+    }
+
+    fn add(&mut self, rectangle: Rectangle<T>) {
+        let area = rectangle.area();
+        self.count += 1;
+        self.total_area = T::saturating_add_area(self.total_area, area);
+        if self.smallest.is_none_or(|smallest| area < smallest.area()) {
+            self.smallest = Some(rectangle);
+        }
+        if self.largest.is_none_or(|largest| area > largest.area()) {
+            self.largest = Some(rectangle);
+        }
+    }
+}
+
+/// The rectangles whose area is at most `max_area`, and more than the previous class's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeClass<T: Dimension> {
+    /// `None` for the last class, which takes everything larger.
+    pub max_area: Option<T::Area>,
+    pub rectangles: Vec<Rectangle<T>>,
+}
+
+/// Splits `rectangles` into size classes at the areas in `bounds`, which must be increasing.
+///
+/// There is one class per bound plus one for the rest, so `[100, 1000]` gives the classes
+/// up to 100, over 100 up to 1000, and over 1000. Rectangles keep their order within a class.
+pub fn group_by_area<T: Dimension>(rectangles: &[Rectangle<T>], bounds: &[T::Area]) -> Vec<SizeClass<T>> {
+    let mut classes: Vec<SizeClass<T>> = bounds
+        .iter()
+        .map(|bound| Some(*bound))
+        .chain([None])
+        .map(|max_area| SizeClass { max_area, rectangles: vec![] })
+        .collect();
+
+    for rectangle in rectangles {
+        let area = rectangle.area();
+        let class = bounds.iter().position(|bound| area <= *bound).unwrap_or(bounds.len());
+        classes[class].rectangles.push(*rectangle);
+    }
+    classes
+}
+
+/*
+    A report over a set of rectangles: a summary of all of them and one per size class.
+
+    As CSV, one row per class and a last row for everything, with the extremes written as
+    WIDTHxHEIGHT and left empty when a class has no rectangles:
+
+        class,count,total_area,smallest,largest
+        <=100,2,65,3x5,5x10
+        >100,1,84,7x12,7x12
+        all,3,149,3x5,7x12
+
+    As JSON, an object with the summary of all rectangles and a "classes" array of summaries,
+    where rectangles are {"width": .., "height": ..} and missing ones are null. JSON has no
+    infinity or NaN, so float numbers that are either, like a total area that saturated, are
+    written as null too.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report<T: Dimension> {
+    pub all: Summary<T>,
+    pub classes: Vec<(Option<T::Area>, Summary<T>)>,
+}
+
+impl<T: Dimension> Report<T> {
+    /// Summarises `rectangles` overall and in the size classes given by `bounds`.
+    pub fn new(rectangles: &[Rectangle<T>], bounds: &[T::Area]) -> Report<T> {
+        Report {
+            all: Summary::of(rectangles),
+            classes: group_by_area(rectangles, bounds)
+                .into_iter()
+                .map(|class| (class.max_area, Summary::of(&class.rectangles)))
+                .collect(),
+        }
+    }
+
+    // The CSV name of each class: "<=100" for a bound, ">100" for the rest after the last bound.
+    fn class_labels(&self) -> Vec<String> {
+        let mut previous = None;
+        self.classes
+            .iter()
+            .map(|(max_area, _)| {
+                let label = match (max_area, previous) {
+                    (Some(bound), _) => format!("<={bound}"),
+                    (None, Some(bound)) => format!(">{bound}"),
+                    (None, None) => String::from("any"),
+                };
+                previous = *max_area;
+                label
+            })
+            .collect()
+    }
+
+    pub fn write_csv<W: Write>(&self, mut output: W) -> io::Result<()> {
+        let side = |rectangle: Option<Rectangle<T>>| {
+            rectangle.map_or(String::new(), |r| format!("{}x{}", r.width.number(), r.height().number()))
+        };
+        let rows = self
+            .class_labels()
+            .into_iter()
+            .zip(self.classes.iter().map(|(_, summary)| summary))
+            .chain([(String::from("all"), &self.all)]);
+
+        writeln!(output, "class,count,total_area,smallest,largest")?;
+        for (label, summary) in rows {
+            writeln!(
+                output,
+                "{label},{},{},{},{}",
+                summary.count,
+                summary.total_area,
+                side(summary.smallest),
+                side(summary.largest)
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, mut output: W) -> io::Result<()> {
+        let side = |rectangle: Option<Rectangle<T>>| {
+            rectangle.map_or(String::from("null"), |r| {
+                let (width, height) = (json::number(r.width.number()), json::number(r.height().number()));
+                json::object(&[("width", &width), ("height", &height)])
+            })
+        };
+        let summary = |summary: &Summary<T>| {
+            let (smallest, largest) = (side(summary.smallest), side(summary.largest));
+            json::members(&[
+                ("count", &summary.count),
+                ("total_area", &json::number(summary.total_area)),
+                ("smallest", &smallest),
+                ("largest", &largest),
+            ])
+        };
+
+        writeln!(output, "{{")?;
+        writeln!(output, "  {},", summary(&self.all))?;
+        writeln!(output, r#"  "classes": ["#)?;
+        for (index, (max_area, class)) in self.classes.iter().enumerate() {
+            let max_area = max_area.map_or(String::from("null"), json::number);
+            let separator = if index + 1 < self.classes.len() { "," } else { "" };
+            writeln!(output, r#"    {{"max_area": {max_area}, {}}}{separator}"#, summary(class))?;
+        }
+        writeln!(output, "  ]")?;
+        writeln!(output, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rectangle::Millimeters;
+
+    fn rectangles() -> Vec<Rectangle> {
+        vec![Rectangle::new(10, 1), Rectangle::new(3, 5), Rectangle::new(7, 12), Rectangle::new(5, 2)]
+    }
+
+    fn widths(rectangles: &[Rectangle]) -> Vec<u32> {
+        rectangles.iter().map(|r| r.width).collect()
+    }
+
+    #[test]
+    fn sorts_by_each_order() {
+        let mut list = rectangles();
+
+        sort_by(&mut list, Order::Area);
+        assert_eq!(widths(&list), vec![10, 5, 3, 7]);
+        sort_by(&mut list, Order::Perimeter);
+        assert_eq!(widths(&list), vec![5, 3, 10, 7]);
+        sort_by(&mut list, Order::AspectRatio);
+        assert_eq!(widths(&list), vec![7, 3, 5, 10]);
+        sort_by(&mut list, Order::Width);
+        assert_eq!(widths(&list), vec![3, 5, 7, 10]);
+    }
+
+    #[test]
+    fn groups_into_size_classes() {
+        let classes = group_by_area(&rectangles(), &[10, 50]);
+
+        assert_eq!(classes.len(), 3);
+        assert_eq!(widths(&classes[0].rectangles), vec![10, 5]);
+        assert_eq!(widths(&classes[1].rectangles), vec![3]);
+        assert_eq!((classes[2].max_area, widths(&classes[2].rectangles)), (None, vec![7]));
+    }
+
+    #[test]
+    fn total_area_saturates_instead_of_overflowing() {
+        let largest = Rectangle::square(u32::MAX);
+        let summary = Summary::of(&[largest, largest, Rectangle::new(1, 1)]);
+
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.total_area, u64::MAX);
+        assert_eq!(summary.largest, Some(largest));
+
+        let floats = Summary::of(&[Rectangle::square(f64::MAX.sqrt()); 3]);
+        assert_eq!(floats.total_area, f64::INFINITY);
+    }
+
+    #[test]
+    fn writes_csv_and_json() {
+        let report = Report::new(&rectangles(), &[10, 50]);
+        assert_eq!(report.all.total_area, 10 + 15 + 84 + 10);
+        assert_eq!(report.all.smallest, Some(Rectangle::new(10, 1)));
+
+        let mut csv = vec![];
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "class,count,total_area,smallest,largest\n\
+             <=10,2,20,10x1,10x1\n\
+             <=50,1,15,3x5,3x5\n\
+             >50,1,84,7x12,7x12\n\
+             all,4,119,10x1,7x12\n"
+        );
+
+        let mut json = vec![];
+        Report::new(&[Rectangle::new(Millimeters(2), Millimeters(3))], &[]).write_json(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#"{
+  "count": 1, "total_area": 6, "smallest": {"width": 2, "height": 3}, "largest": {"width": 2, "height": 3},
+  "classes": [
+    {"max_area": null, "count": 1, "total_area": 6, "smallest": {"width": 2, "height": 3}, "largest": {"width": 2, "height": 3}}
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn writes_infinite_and_nan_floats_as_null() {
+        let mut json = vec![];
+        Report::new(&[Rectangle::square(1.5e19_f32); 2], &[]).write_json(&mut json).unwrap();
+        let side = r#"{"width": 15000000000000000000, "height": 15000000000000000000}"#;
+        let summary = format!(r#""count": 2, "total_area": null, "smallest": {side}, "largest": {side}"#);
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            format!("{{\n  {summary},\n  \"classes\": [\n    {{\"max_area\": null, {summary}}}\n  ]\n}}\n")
+        );
+
+        let mut json = vec![];
+        Report::new(&[Rectangle::new(f64::INFINITY, f64::NAN)], &[f64::NAN]).write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(r#""total_area": null, "smallest": {"width": null, "height": null}"#), "{json}");
+        assert!(!json.contains("inf") && !json.contains("NaN"), "{json}");
+    }
+
+    #[test]
+    fn an_empty_report_has_no_extremes() {
+        let mut csv = vec![];
+        Report::<u32>::new(&[], &[]).write_csv(&mut csv).unwrap();
+
+        assert_eq!(String::from_utf8(csv).unwrap(), "class,count,total_area,smallest,largest\nany,0,0,,\nall,0,0,,\n");
+    }
+}
@@ -0,0 +1,407 @@
+use super::{Point, Positioned};
+use std::collections::{HashMap, HashSet};
+
+// Rectangles that would cover more cells than this are kept in a separate list instead.
+const MAX_CELLS_PER_RECTANGLE: i64 = 64;
+
+/*
+    A uniform grid over the plane. Each rectangle is filed under every cell_size x cell_size cell
+    it covers, so a query only compares the rectangles filed under the cells it touches instead of
+    every rectangle in the index. It works best when most rectangles are around the size of a cell:
+    pick cell_size close to the typical side length.
+
+    Rectangles far bigger than a cell would have to be filed under a huge number of cells, so they
+    go into a short "oversized" list that every query checks directly. Empty rectangles have no
+    points and never match a query, but can be inserted and removed like any other.
+
+    Cells are only allocated once something is filed under them, so a sparse index over a large
+    area stays small.
+ */
+#[derive(Debug, Clone)]
+pub struct GridIndex {
+    cell_size: i64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    oversized: Vec<usize>,
+    rectangles: HashMap<usize, Positioned>,
+    next_id: usize,
+    // Every cell that was ever occupied lies in here; it grows on insert but never shrinks.
+    extent: Option<CellRange>,
+}
+
+// The rows and columns of cells a rectangle or window covers.
+#[derive(Debug, Clone, Copy)]
+struct CellRange {
+    columns: (i64, i64),
+    rows: (i64, i64),
+}
+
+impl CellRange {
+    // Saturates: a range that big is far over MAX_CELLS_PER_RECTANGLE either way.
+    fn count(&self) -> i64 {
+        (self.columns.1 - self.columns.0 + 1).saturating_mul(self.rows.1 - self.rows.0 + 1)
+    }
+
+    fn union(self, other: CellRange) -> CellRange {
+        CellRange {
+            columns: (self.columns.0.min(other.columns.0), self.columns.1.max(other.columns.1)),
+            rows: (self.rows.0.min(other.rows.0), self.rows.1.max(other.rows.1)),
+        }
+    }
+
+    fn cells(self) -> impl Iterator<Item = (i64, i64)> {
+        (self.columns.0..=self.columns.1).flat_map(move |column| (self.rows.0..=self.rows.1).map(move |row| (column, row)))
+    }
+}
+
+impl GridIndex {
+    /// # Panics
+    ///
+    /// If `cell_size` is 0.
+    pub fn new(cell_size: u32) -> GridIndex {
+        assert!(cell_size > 0, "the cells of a grid index need a size");
+        GridIndex {
+            cell_size: cell_size as i64,
+            cells: HashMap::new(),
+            oversized: vec![],
+            rectangles: HashMap::new(),
+            next_id: 0,
+            extent: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rectangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rectangles.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Positioned> {
+        self.rectangles.get(&id)
+    }
+
+    fn cell_range(&self, rectangle: &Positioned) -> CellRange {
+        // An empty rectangle is filed under the cell of its origin.
+        let last = |start: i64, end: i64| (end - 1).max(start);
+        CellRange {
+            columns: (
+                rectangle.left().div_euclid(self.cell_size),
+                last(rectangle.left(), rectangle.right()).div_euclid(self.cell_size),
+            ),
+            rows: (
+                rectangle.top().div_euclid(self.cell_size),
+                last(rectangle.top(), rectangle.bottom()).div_euclid(self.cell_size),
+            ),
+        }
+    }
+
+    /// Adds `rectangle` and returns the id to remove it by.
+    pub fn insert(&mut self, rectangle: Positioned) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.rectangles.insert(id, rectangle);
+
+        let range = self.cell_range(&rectangle);
+        if range.count() > MAX_CELLS_PER_RECTANGLE {
+            self.oversized.push(id);
+        } else {
+            for cell in range.cells() {
+                self.cells.entry(cell).or_default().push(id);
+            }
+            self.extent = Some(match self.extent {
+                Some(extent) => extent.union(range),
+                None => range,
+            });
+        }
+        id
+    }
+
+    /// Removes the rectangle inserted as `id` and returns it, if it is still in the index.
+    pub fn remove(&mut self, id: usize) -> Option<Positioned> {
+        let rectangle = self.rectangles.remove(&id)?;
+
+        let range = self.cell_range(&rectangle);
+        if range.count() > MAX_CELLS_PER_RECTANGLE {
+            self.oversized.retain(|&other| other != id);
+        } else {
+            for cell in range.cells() {
+                if let Some(ids) = self.cells.get_mut(&cell) {
+                    ids.retain(|&other| other != id);
+                    if ids.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+        Some(rectangle)
+    }
+
+    // Every id filed under a cell of `range`, plus the oversized ones; may contain duplicates.
+    fn candidates(&self, range: CellRange) -> Vec<usize> {
+        let mut candidates = self.oversized.clone();
+        if range.count() as u64 > self.cells.len() as u64 {
+            // A window bigger than the occupied part of the grid: look at the occupied cells.
+            let (columns, rows) = (range.columns.0..=range.columns.1, range.rows.0..=range.rows.1);
+            for ((column, row), ids) in &self.cells {
+                if columns.contains(column) && rows.contains(row) {
+                    candidates.extend(ids);
+                }
+            }
+        } else {
+            for cell in range.cells() {
+                candidates.extend(self.cells.get(&cell).into_iter().flatten());
+            }
+        }
+        candidates
+    }
+
+    /// The ids of the rectangles that intersect `window`, in increasing order.
+    pub fn query(&self, window: &Positioned) -> Vec<usize> {
+        if window.is_empty() {
+            return vec![];
+        }
+        let mut found: Vec<usize> = self
+            .candidates(self.cell_range(window))
+            .into_iter()
+            .filter(|id| self.rectangles[id].intersects(window))
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    /*
+        Searches outwards from the cell of `point`, one ring of cells at a time. Every rectangle
+        not yet seen after ring r lies entirely in cells at least r + 1 away, so at least
+        r * cell_size + 1 from the point: once the best distance found is no more than that,
+        nothing further out can beat it.
+     */
+    /// The rectangle closest to `point` and its squared distance; ties go to the lowest id.
+    pub fn nearest(&self, point: Point) -> Option<(usize, u128)> {
+        let mut best: Option<(u128, usize)> = None;
+        let consider = |best: &mut Option<(u128, usize)>, id: usize| {
+            if let Some(distance) = self.rectangles[&id].distance_squared(point) {
+                if best.is_none_or(|best| (distance, id) < best) {
+                    *best = Some((distance, id));
+                }
+            }
+        };
+        for &id in &self.oversized {
+            consider(&mut best, id);
+        }
+
+        let origin = (
+            (point.x as i64).div_euclid(self.cell_size),
+            (point.y as i64).div_euclid(self.cell_size),
+        );
+        // Rings closer than the first or further than the last can't hold an occupied cell.
+        let rings = self.extent.filter(|_| !self.cells.is_empty()).map(|extent| {
+            let gap = |low: i64, high: i64, at: i64| (low - at).max(at - high).max(0);
+            let far = |low: i64, high: i64, at: i64| (low - at).abs().max((high - at).abs());
+            let first = gap(extent.columns.0, extent.columns.1, origin.0).max(gap(extent.rows.0, extent.rows.1, origin.1));
+            let last = far(extent.columns.0, extent.columns.1, origin.0).max(far(extent.rows.0, extent.rows.1, origin.1));
+            first..=last
+        });
+
+        if let Some(rings) = rings {
+            for ring in rings {
+                // Past this ring there are more cells to a ring than occupied cells: look at those.
+                if 8 * ring > self.cells.len() as i64 {
+                    for &id in self.cells.values().flatten() {
+                        consider(&mut best, id);
+                    }
+                    break;
+                }
+                for cell in ring_cells(origin, ring) {
+                    for &id in self.cells.get(&cell).into_iter().flatten() {
+                        consider(&mut best, id);
+                    }
+                }
+                let reach = (ring * self.cell_size) as u128;
+                if best.is_some_and(|(distance, _)| distance <= reach * reach) {
+                    break;
+                }
+            }
+        }
+
+        best.map(|(distance, id)| (id, distance))
+    }
+
+    /// Every pair of rectangles that intersect, as (lower id, higher id) in increasing order.
+    pub fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = HashSet::new();
+        let mut check = |a: usize, b: usize| {
+            let pair = (a.min(b), a.max(b));
+            if a != b && !pairs.contains(&pair) && self.rectangles[&a].intersects(&self.rectangles[&b]) {
+                pairs.insert(pair);
+            }
+        };
+
+        for ids in self.cells.values() {
+            for (index, &a) in ids.iter().enumerate() {
+                for &b in &ids[index + 1..] {
+                    check(a, b);
+                }
+            }
+        }
+        for &a in &self.oversized {
+            for &b in self.rectangles.keys() {
+                check(a, b);
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+        pairs.sort();
+        pairs
+    }
+}
+
+// The cells exactly `ring` cells away from `origin`, measured in the larger of the two axes.
+fn ring_cells(origin: (i64, i64), ring: i64) -> Vec<(i64, i64)> {
+    if ring == 0 {
+        return vec![origin];
+    }
+    let (x, y) = origin;
+    let mut cells = vec![];
+    for offset in -ring..=ring {
+        cells.push((x + offset, y - ring));
+        cells.push((x + offset, y + ring));
+    }
+    for offset in -ring + 1..ring {
+        cells.push((x - ring, y + offset));
+        cells.push((x + ring, y + offset));
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn finds_what_was_inserted_until_it_is_removed() {
+        let mut index = GridIndex::new(10);
+        let small = index.insert(Positioned::new(5, 5, 10, 10));
+        let huge = index.insert(Positioned::new(-1_000, -1_000, 5_000, 5_000));
+        let empty = index.insert(Positioned::new(7, 7, 0, 0));
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.query(&Positioned::new(0, 0, 6, 6)), vec![small, huge]);
+        assert_eq!(index.query(&Positioned::new(100, 100, 1, 1)), vec![huge]);
+        assert_eq!(index.overlapping_pairs(), vec![(small, huge)]);
+        assert_eq!(index.nearest(Point::new(7, 7)), Some((small, 0)));
+
+        assert_eq!(index.remove(huge), Some(Positioned::new(-1_000, -1_000, 5_000, 5_000)));
+        assert_eq!(index.remove(huge), None);
+        assert_eq!(index.nearest(Point::new(20, 0)), Some((small, 6 * 6 + 5 * 5)));
+        assert_eq!(index.remove(empty), Some(Positioned::new(7, 7, 0, 0)));
+        assert_eq!(index.remove(small).map(|rectangle| rectangle.origin), Some(Point::new(5, 5)));
+        assert!(index.is_empty());
+        assert_eq!(index.nearest(Point::new(0, 0)), None);
+    }
+
+    #[test]
+    fn handles_rectangles_as_big_as_the_plane() {
+        let mut index = GridIndex::new(1);
+        let plane = index.insert(Positioned::new(i32::MIN, i32::MIN, u32::MAX, u32::MAX));
+        let corner = index.insert(Positioned::new(i32::MAX - 1, i32::MAX - 1, 1, 1));
+
+        assert_eq!(index.query(&Positioned::new(0, 0, 1, 1)), vec![plane]);
+        assert_eq!(index.overlapping_pairs(), vec![(plane, corner)]);
+        assert_eq!(index.nearest(Point::new(i32::MAX, i32::MAX)), Some((plane, 2)));
+        assert_eq!(index.remove(plane), Some(Positioned::new(i32::MIN, i32::MIN, u32::MAX, u32::MAX)));
+        assert_eq!(index.nearest(Point::new(i32::MAX, i32::MAX)), Some((corner, 2)));
+        assert_eq!(index.nearest(Point::new(0, 0)).map(|(id, _)| id), Some(corner));
+    }
+
+    #[test]
+    fn nearest_looks_past_empty_rings() {
+        let mut index = GridIndex::new(4);
+        let far = index.insert(Positioned::new(-90, 3, 2, 2));
+        index.insert(Positioned::new(120, 0, 1, 1));
+
+        assert_eq!(index.nearest(Point::new(0, 0)), Some((far, 89 * 89 + 9)));
+    }
+
+    #[derive(Debug, Clone)]
+    enum Operation {
+        Insert(Positioned),
+        Remove(usize),
+        Query(Positioned),
+        Nearest(Point),
+    }
+
+    // Coordinates near the ends of i32 and sizes near the end of u32.
+    fn extreme() -> impl Strategy<Value = (i32, i32, u32, u32)> {
+        let coordinate = prop_oneof![Just(i32::MIN), Just(i32::MAX), i32::MIN..i32::MIN + 50, i32::MAX - 50..=i32::MAX, any::<i32>()];
+        let size = prop_oneof![Just(u32::MAX), 0..30u32, any::<u32>()];
+        (coordinate.clone(), coordinate, size.clone(), size)
+    }
+
+    fn positioned() -> impl Strategy<Value = Positioned> {
+        prop_oneof![
+            4 => (-100..100i32, -100..100i32, 0..30u32, 0..30u32),
+            1 => (-300..300i32, -300..300i32, 0..400u32, 0..400u32),
+            1 => extreme(),
+        ]
+        .prop_map(|(x, y, width, height)| Positioned::new(x, y, width, height))
+    }
+
+    fn operation() -> impl Strategy<Value = Operation> {
+        prop_oneof![
+            4 => positioned().prop_map(Operation::Insert),
+            1 => (0..60usize).prop_map(Operation::Remove),
+            2 => positioned().prop_map(Operation::Query),
+            2 => (-400..400i32, -400..400i32).prop_map(|(x, y)| Operation::Nearest(Point::new(x, y))),
+            1 => any::<(i32, i32)>().prop_map(|(x, y)| Operation::Nearest(Point::new(x, y))),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn agrees_with_brute_force(cell_size in 1..40u32, operations in prop::collection::vec(operation(), 1..80)) {
+            let mut index = GridIndex::new(cell_size);
+            let mut all: Vec<Option<Positioned>> = vec![];
+
+            for operation in operations {
+                match operation {
+                    Operation::Insert(rectangle) => {
+                        prop_assert_eq!(index.insert(rectangle), all.len());
+                        all.push(Some(rectangle));
+                    }
+                    Operation::Remove(id) => {
+                        let expected = all.get_mut(id).and_then(Option::take);
+                        prop_assert_eq!(index.remove(id), expected);
+                    }
+                    Operation::Query(window) => {
+                        let expected: Vec<usize> = (0..all.len())
+                            .filter(|&id| all[id].is_some_and(|rectangle| rectangle.intersects(&window)))
+                            .collect();
+                        prop_assert_eq!(index.query(&window), expected);
+                    }
+                    Operation::Nearest(point) => {
+                        let expected = (0..all.len())
+                            .filter_map(|id| Some((all[id]?.distance_squared(point)?, id)))
+                            .min()
+                            .map(|(distance, id)| (id, distance));
+                        prop_assert_eq!(index.nearest(point), expected);
+                    }
+                }
+            }
+
+            let mut expected = vec![];
+            for a in 0..all.len() {
+                for b in a + 1..all.len() {
+                    if let (Some(first), Some(second)) = (all[a], all[b]) {
+                        if first.intersects(&second) {
+                            expected.push((a, b));
+                        }
+                    }
+                }
+            }
+            prop_assert_eq!(index.overlapping_pairs(), expected);
+        }
+    }
+}
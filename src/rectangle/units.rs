@@ -0,0 +1,118 @@
+use super::Dimension;
+use std::fmt;
+use std::ops::{Add, Mul};
+
+/*
+    Lengths tagged with their unit, so a Rectangle<Millimeters> can't be mixed up with a
+    Rectangle<Meters>. Metres convert to millimetres without loss, so that direction is allowed,
+    both with From and with +. The other direction would have to round, so there is none.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Millimeters<T = u32>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Meters<T = u32>(pub T);
+
+const MILLIMETERS_PER_METER: u16 = 1_000;
+
+// A unit-tagged side measures like its number: areas in the square of its unit, perimeters in the unit.
+macro_rules! unit_dimension {
+    ($($unit:ident => $symbol:literal),*) => {$(
+        impl<T: Dimension> Dimension for $unit<T> {
+            type Area = T::Area;
+            type Number = T::Number;
+
+            fn area(width: $unit<T>, height: $unit<T>) -> T::Area {
+                T::area(width.0, height.0)
+            }
+
+            fn checked_area(width: $unit<T>, height: $unit<T>) -> Option<T::Area> {
+                T::checked_area(width.0, height.0)
+            }
+
+            fn perimeter(width: $unit<T>, height: $unit<T>) -> T::Area {
+                T::perimeter(width.0, height.0)
+            }
+
+            fn saturating_add_area(a: T::Area, b: T::Area) -> T::Area {
+                T::saturating_add_area(a, b)
+            }
+
+            fn number(self) -> T::Number {
+                self.0.number()
+            }
+
+            fn to_f64(self) -> f64 {
+                self.0.to_f64()
+            }
+        }
+
+        impl<T: Add<Output = T>> Add for $unit<T> {
+            type Output = $unit<T>;
+
+            fn add(self, other: $unit<T>) -> $unit<T> {
+                $unit(self.0 + other.0)
+            }
+        }
+
+        impl<T: fmt::Display> fmt::Display for $unit<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{} {}", self.0, $symbol)
+            }
+        }
+    )*};
+}
+
+unit_dimension!(Millimeters => "mm", Meters => "m");
+
+impl<T: Mul<Output = T> + From<u16>> From<Meters<T>> for Millimeters<T> {
+    fn from(meters: Meters<T>) -> Millimeters<T> {
+        Millimeters(meters.0 * T::from(MILLIMETERS_PER_METER))
+    }
+}
+
+/// # Examples
+///
+/// ```
+/// use rust_book::rectangle::{Meters, Millimeters, Rectangle};
+///
+/// let tile = Rectangle::new(Millimeters(500), Millimeters(250));
+/// let floor = Rectangle::new(Meters(2), Meters(1));
+/// assert_eq!(tile + floor, Rectangle::new(Millimeters(2_500), Millimeters(1_250)));
+/// assert_eq!(floor.convert::<Millimeters>().area(), 2_000_000);
+/// ```
+///
+/// Adding millimetres to metres doesn't compile:
+///
+/// ```compile_fail
+/// use rust_book::rectangle::{Meters, Millimeters, Rectangle};
+///
+/// let floor = Rectangle::new(Meters(2), Meters(1));
+/// let tile = Rectangle::new(Millimeters(500), Millimeters(250));
+/// let _ = floor + tile;
+/// ```
+impl<T: Add<Output = T> + Mul<Output = T> + From<u16>> Add<Meters<T>> for Millimeters<T> {
+    type Output = Millimeters<T>;
+
+    fn add(self, other: Meters<T>) -> Millimeters<T> {
+        self + Millimeters::from(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rectangle::Rectangle;
+
+    #[test]
+    fn metres_convert_to_millimetres() {
+        assert_eq!(Millimeters(500) + Meters(1), Millimeters(1_500));
+        assert_eq!(Millimeters::from(Meters(0.25)), Millimeters(250.0));
+
+        let sheet: Rectangle<Meters<f64>> = Rectangle::new(Meters(1.5), Meters(0.5));
+        let margin = Rectangle::new(Millimeters(20.0), Millimeters(20.0));
+        assert_eq!(margin + sheet, Rectangle::new(Millimeters(1_520.0), Millimeters(520.0)));
+        assert_eq!(sheet.area(), 0.75);
+        assert_eq!(Meters(3).to_string(), "3 m");
+    }
+}
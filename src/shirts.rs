@@ -1,44 +1,394 @@
-#[derive(Debug, PartialEq, Copy, Clone)]
+pub mod reservations;
+pub mod strategy;
+
+pub use self::reservations::SharedInventory;
+pub use self::strategy::Strategy;
+
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+/*
+    A shirt in stock is a SKU (stock keeping unit): one combination of colour, size and style.
+    Colours, sizes and styles are attributes; the inventory counts shirts per SKU and per value
+    of each attribute, so it can say which colour, size, style or SKU it has most of.
+
+    Nothing matches on the values of an attribute, so a new colour is just a new variant.
+    Declaration order matters in one place: it breaks ties between equally stocked values.
+ */
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
 pub enum ShirtColor {
     Red,
     Blue,
+    Green,
+    Black,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub enum Size {
+    Small,
+    Medium,
+    Large,
+    ExtraLarge,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub enum Style {
+    Crew,
+    VNeck,
+    Polo,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub struct Sku {
+    pub color: ShirtColor,
+    pub size: Size,
+    pub style: Style,
+}
+
+impl Sku {
+    pub fn new(color: ShirtColor, size: Size, style: Style) -> Sku {
+        Sku { color, size, style }
+    }
+}
+
+impl fmt::Display for Sku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {:?} {:?}", self.color, self.size, self.style)
+    }
+}
+
+/// Something the inventory counts shirts by: an attribute like `ShirtColor`, or `Sku` itself.
+pub trait Attribute: Copy + Eq + Hash + Ord + fmt::Debug {
+    fn of(sku: &Sku) -> Self;
+
+    fn tally(inventory: &Inventory) -> &Tally<Self>;
+
+    fn tally_mut(inventory: &mut Inventory) -> &mut Tally<Self>;
+}
+
+macro_rules! attribute {
+    ($($attribute:ty => $field:ident from $sku:ident => $value:expr),*) => {$(
+        impl Attribute for $attribute {
+            fn of($sku: &Sku) -> $attribute {
+                $value
+            }
+
+            fn tally(inventory: &Inventory) -> &Tally<$attribute> {
+                &inventory.$field
+            }
+
+            fn tally_mut(inventory: &mut Inventory) -> &mut Tally<$attribute> {
+                &mut inventory.$field
+            }
+        }
+    )*};
 }
 
+attribute!(
+    Sku => skus from sku => *sku,
+    ShirtColor => colors from sku => sku.color,
+    Size => sizes from sku => sku.size,
+    Style => styles from sku => sku.style
+);
+
+/*
+    Shirt counts per value, with the values grouped by count so the most stocked one is always
+    at hand. Stock only ever goes down one shirt at a time, so when the largest group empties,
+    the value that left it is in the group just below: keeping track of the largest count never
+    needs a search, and `most_stocked` costs the same however many shirts there are.
+ */
+#[derive(Debug, Clone)]
+pub struct Tally<V> {
+    counts: HashMap<V, usize>,
+    // Values by count; values with no shirts left aren't in any group.
+    by_count: HashMap<usize, BTreeSet<V>>,
+    max: usize,
+    total: usize,
+}
+
+impl<V> Default for Tally<V> {
+    fn default() -> Tally<V> {
+        Tally { counts: HashMap::new(), by_count: HashMap::new(), max: 0, total: 0 }
+    }
+}
+
+impl<V: Copy + Eq + Hash + Ord> Tally<V> {
+    pub fn count(&self, value: V) -> usize {
+        self.counts.get(&value).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The value with the most shirts, or `None` if there are none.
+    ///
+    /// Ties go to the smallest value, which for the attribute enums is the one declared first.
+    pub fn most_stocked(&self) -> Option<V> {
+        self.by_count.get(&self.max)?.first().copied()
+    }
+
+    /// Values with at least one shirt, and how many.
+    pub fn iter(&self) -> impl Iterator<Item = (V, usize)> + '_ {
+        self.counts.iter().map(|(value, count)| (*value, *count))
+    }
+
+    fn add(&mut self, value: V, shirts: usize) {
+        if shirts == 0 {
+            return;
+        }
+        let count = self.count(value);
+        self.regroup(value, count, count + shirts);
+        self.max = self.max.max(count + shirts);
+        self.total += shirts;
+    }
+
+    fn remove_one(&mut self, value: V) {
+        let count = self.count(value);
+        assert!(count > 0, "no shirts to remove");
+        self.regroup(value, count, count - 1);
+        if self.max == count && !self.by_count.contains_key(&count) {
+            self.max = count - 1;
+        }
+        self.total -= 1;
+    }
+
+    fn regroup(&mut self, value: V, from: usize, to: usize) {
+        if let Some(group) = self.by_count.get_mut(&from) {
+            group.remove(&value);
+            if group.is_empty() {
+                self.by_count.remove(&from);
+            }
+        }
+        if to == 0 {
+            self.counts.remove(&value);
+        } else {
+            self.counts.insert(value, to);
+            self.by_count.entry(to).or_default().insert(value);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GiveawayError {
+    /// The user asked for a colour that has run out.
+    OutOfStock(ShirtColor),
+    /// Every colour the user would take, in their order of preference, has run out.
+    PreferencesOutOfStock(Vec<ShirtColor>),
+    /// There are shirts left, but none the strategy is allowed to hand out.
+    NoEligibleColor,
+    /// The user had no preference and there are no shirts left at all.
+    SoldOut,
+}
+
+impl fmt::Display for GiveawayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GiveawayError::OutOfStock(color) => write!(f, "no {color:?} shirts left"),
+            GiveawayError::PreferencesOutOfStock(colors) => {
+                let colors: Vec<String> = colors.iter().map(|color| format!("{color:?}")).collect();
+                write!(f, "no {} shirts left", colors.join(" or "))
+            }
+            GiveawayError::NoEligibleColor => write!(f, "no shirts of the colours this giveaway hands out left"),
+            GiveawayError::SoldOut => write!(f, "no shirts left"),
+        }
+    }
+}
+
+impl Error for GiveawayError {}
+
+#[derive(Debug, Clone, Default)]
 pub struct Inventory {
-    pub shirts: Vec<ShirtColor>,
+    skus: Tally<Sku>,
+    colors: Tally<ShirtColor>,
+    sizes: Tally<Size>,
+    styles: Tally<Style>,
 }
 
 impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory::default()
+    }
+
+    pub fn restock(&mut self, sku: Sku, shirts: usize) {
+        self.skus.add(sku, shirts);
+        self.colors.add(sku.color, shirts);
+        self.sizes.add(sku.size, shirts);
+        self.styles.add(sku.style, shirts);
+    }
+
+    /// The counts by one attribute, like `inventory.tally::<Size>()`.
+    pub fn tally<A: Attribute>(&self) -> &Tally<A> {
+        A::tally(self)
+    }
+
+    pub fn count<A: Attribute>(&self, value: A) -> usize {
+        self.tally().count(value)
+    }
+
+    pub fn total(&self) -> usize {
+        self.skus.total()
+    }
+
+    /// The most stocked value of an attribute; ties go to the value declared first.
+    pub fn most_stocked<A: Attribute>(&self) -> Option<A> {
+        self.tally().most_stocked()
+    }
+
     /*
-        The unwrap_or_else method on Option<T> is defined by the standard library.
-        It takes one argument: a closure without any arguments that returns a value T
-        (the same type stored in the Some variant of the Option<T>, in this case ShirtColor).
-        If the Option<T> is the Some variant, unwrap_or_else returns the value from within the Some.
-        If the Option<T> is the None variant, unwrap_or_else calls the closure and returns the value
-        returned by the closure. If the closure had parameters, they would appear between the two
-        vertical bars. The closure captures an immutable reference to the self Inventory instance
-        and passes it with the code we specify to the unwrap_or_else method.
-        Functions, on the other hand, are not able to capture their environment in this way.
+        A giveaway takes the shirt out of the inventory, so it needs &mut self. A user who names a
+        colour gets that colour or an error; they aren't sent home with another one. Within the
+        colour, they get the most stocked SKU, and of equally stocked SKUs the smallest size, then
+        the first style. The choice itself is made by strategy::MostStocked, whose closure falls
+        back to the most stocked colour when the user has no preference.
      */
-    pub fn giveaway(&self, user_preference: Option<ShirtColor>) -> ShirtColor {
-        user_preference.unwrap_or_else(|| self.most_stocked())
+    pub fn giveaway(&mut self, user_preference: Option<ShirtColor>) -> Result<Sku, GiveawayError> {
+        self.giveaway_with(&mut strategy::MostStocked, user_preference.as_slice())
     }
 
-    fn most_stocked(&self) -> ShirtColor {
-        let mut num_red = 0;
-        let mut num_blue = 0;
-
-        for color in &self.shirts {
-            match color {
-                ShirtColor::Red => num_red += 1,
-                ShirtColor::Blue => num_blue += 1,
-            }
+    /// Gives away the shirt `strategy` chooses for a user with these ranked `preferences`.
+    pub fn giveaway_with(
+        &mut self,
+        strategy: &mut dyn Strategy,
+        preferences: &[ShirtColor],
+    ) -> Result<Sku, GiveawayError> {
+        let sku = strategy.choose(self, preferences)?;
+        // Strategies can come from anywhere, so don't trust them to pick a shirt we have.
+        if self.skus.count(sku) == 0 {
+            return Err(GiveawayError::OutOfStock(sku.color));
         }
-        if num_red > num_blue {
-            ShirtColor::Red
-        } else {
-            ShirtColor::Blue
+        self.take(sku);
+        Ok(sku)
+    }
+
+    /// The SKU to give someone who wants `color`: the most stocked one, and of equally stocked
+    /// SKUs the smallest size, then the first style. `None` if the colour has run out.
+    pub fn best_of(&self, color: ShirtColor) -> Option<Sku> {
+        self.skus
+            .iter()
+            .filter(|(sku, _)| sku.color == color)
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+            .map(|(sku, _)| sku)
+    }
+
+    // Takes one shirt of `sku`, which must be in stock, out of every tally.
+    fn take(&mut self, sku: Sku) {
+        self.skus.remove_one(sku);
+        self.colors.remove_one(sku.color);
+        self.sizes.remove_one(sku.size);
+        self.styles.remove_one(sku.style);
+    }
+}
+
+impl Extend<Sku> for Inventory {
+    fn extend<I: IntoIterator<Item = Sku>>(&mut self, skus: I) {
+        for sku in skus {
+            self.restock(sku, 1);
         }
     }
 }
 
+impl FromIterator<Sku> for Inventory {
+    fn from_iter<I: IntoIterator<Item = Sku>>(skus: I) -> Inventory {
+        let mut inventory = Inventory::new();
+        inventory.extend(skus);
+        inventory
+    }
+}
+
+// Shirts for the tests of this module and of the strategies and reservations.
+#[cfg(test)]
+mod fixtures {
+    use super::*;
+    use ShirtColor::*;
+
+    pub(super) fn crew(color: ShirtColor, size: Size) -> Sku {
+        Sku::new(color, size, Style::Crew)
+    }
+
+    /// Ten crew necks: 2 Red Small, 4 Blue Medium, 1 Blue Large and 3 Green Large.
+    pub(super) fn stock() -> Inventory {
+        let mut inventory = Inventory::new();
+        inventory.restock(crew(Red, Size::Small), 2);
+        inventory.restock(crew(Blue, Size::Medium), 4);
+        inventory.restock(crew(Blue, Size::Large), 1);
+        inventory.restock(crew(Green, Size::Large), 3);
+        inventory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::crew;
+    use super::*;
+    use proptest::prelude::{prop, prop_assert_eq, proptest};
+    use ShirtColor::*;
+
+    #[test]
+    fn giveaways_take_shirts_out_of_stock() {
+        let mut store: Inventory = [crew(Blue, Size::Medium), crew(Red, Size::Small), crew(Blue, Size::Large)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(store.giveaway(Some(Red)), Ok(crew(Red, Size::Small)));
+        assert_eq!(store.giveaway(Some(Red)), Err(GiveawayError::OutOfStock(Red)));
+        assert_eq!(store.giveaway(None), Ok(crew(Blue, Size::Medium)));
+        assert_eq!(store.giveaway(None), Ok(crew(Blue, Size::Large)));
+        assert_eq!(store.giveaway(None), Err(GiveawayError::SoldOut));
+        assert_eq!(store.total(), 0);
+    }
+
+    #[test]
+    fn counts_by_every_attribute() {
+        let mut store = Inventory::new();
+        store.restock(Sku::new(Green, Size::Large, Style::Polo), 3);
+        store.restock(Sku::new(Red, Size::Large, Style::VNeck), 2);
+        store.restock(Sku::new(Red, Size::Small, Style::Polo), 2);
+
+        assert_eq!(store.most_stocked(), Some(Red));
+        assert_eq!(store.most_stocked(), Some(Size::Large));
+        assert_eq!(store.most_stocked(), Some(Style::Polo));
+        assert_eq!(store.most_stocked(), Some(Sku::new(Green, Size::Large, Style::Polo)));
+        assert_eq!((store.count(Size::Large), store.count(Black)), (5, 0));
+        assert_eq!(store.tally::<ShirtColor>().total(), 7);
+    }
+
+    #[test]
+    fn ties_go_to_the_first_declared_value() {
+        let mut store: Inventory = [crew(Blue, Size::Small), crew(Red, Size::Small)].into_iter().collect();
+        assert_eq!(store.most_stocked(), Some(Red));
+
+        store.restock(crew(Blue, Size::Small), 1);
+        assert_eq!(store.most_stocked(), Some(Blue));
+        assert_eq!(Inventory::new().most_stocked::<ShirtColor>(), None);
+    }
+
+    fn color() -> impl proptest::strategy::Strategy<Value = ShirtColor> {
+        prop::sample::select(vec![Red, Blue, Green, Black])
+    }
+
+    proptest! {
+        // The grouped counts agree with counting every colour after any restocks and giveaways.
+        #[test]
+        fn most_stocked_matches_a_full_count(
+            steps in prop::collection::vec((color(), 0..4usize, prop::option::of(color())), 0..40)
+        ) {
+            let mut store = Inventory::new();
+            for (restocked, shirts, preference) in steps {
+                store.restock(crew(restocked, Size::Medium), shirts);
+                let _ = store.giveaway(preference);
+
+                let counts = [Red, Blue, Green, Black].map(|color| (store.count(color), color));
+                let expected = counts
+                    .iter()
+                    .filter(|(count, _)| *count > 0)
+                    .max_by(|(a, a_color), (b, b_color)| a.cmp(b).then(b_color.cmp(a_color)))
+                    .map(|(_, color)| *color);
+                prop_assert_eq!(store.most_stocked(), expected);
+                prop_assert_eq!(store.total(), counts.iter().map(|(count, _)| count).sum::<usize>());
+            }
+        }
+    }
+}
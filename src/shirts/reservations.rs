@@ -0,0 +1,309 @@
+use super::strategy::MostStocked;
+use super::{GiveawayError, Inventory, ShirtColor, Sku, Strategy};
+use crate::clock::{Clock, SystemClock};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/*
+    At an event, several kiosks hand out shirts from the same stock at once. A kiosk first
+    reserves a shirt, which takes it out of the stock the other kiosks see, and then confirms
+    the reservation when the shirt is handed over or cancels it to put the shirt back.
+
+    A kiosk that crashes or is walked away from would hold its shirt forever, so reservations
+    time out: a reservation that isn't confirmed or cancelled within `timeout` is cancelled by
+    the next operation on the inventory. The time comes from a Clock, so tests can use a
+    ManualClock instead of sleeping.
+
+    All the state sits behind one Mutex, and every check and update of an operation happens
+    while holding it, so no two kiosks can ever reserve the same last shirt.
+
+    That includes the caller's Strategy, which may panic and poison the Mutex. A strategy only
+    looks at the stock, and a panicking clock is read before anything changes, so a panic
+    leaves the state as it was and the other kiosks carry on with it instead of panicking too.
+ */
+pub struct SharedInventory<C: Clock = SystemClock> {
+    clock: C,
+    timeout: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ReservationId(u64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReservationError {
+    Giveaway(GiveawayError),
+    /// The reservation was already confirmed, cancelled or timed out.
+    NotHeld(ReservationId),
+}
+
+impl fmt::Display for ReservationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReservationError::Giveaway(error) => write!(f, "{error}"),
+            ReservationError::NotHeld(ReservationId(id)) => write!(f, "reservation {id} is no longer held"),
+        }
+    }
+}
+
+impl Error for ReservationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReservationError::Giveaway(error) => Some(error),
+            ReservationError::NotHeld(_) => None,
+        }
+    }
+}
+
+impl From<GiveawayError> for ReservationError {
+    fn from(error: GiveawayError) -> ReservationError {
+        ReservationError::Giveaway(error)
+    }
+}
+
+struct Hold {
+    sku: Sku,
+    expires: Duration,
+}
+
+struct State {
+    // Shirts that are neither reserved nor given away.
+    available: Inventory,
+    holds: HashMap<ReservationId, Hold>,
+    // The holds again, soonest to expire first.
+    deadlines: BTreeSet<(Duration, ReservationId)>,
+    next_id: u64,
+    given: usize,
+}
+
+impl State {
+    fn release(&mut self, id: ReservationId) -> Option<Sku> {
+        let hold = self.holds.remove(&id)?;
+        self.deadlines.remove(&(hold.expires, id));
+        Some(hold.sku)
+    }
+
+    fn expire(&mut self, now: Duration) {
+        while let Some(&(expires, id)) = self.deadlines.first() {
+            if expires > now {
+                break;
+            }
+            if let Some(sku) = self.release(id) {
+                self.available.restock(sku, 1);
+            }
+        }
+    }
+}
+
+impl SharedInventory<SystemClock> {
+    pub fn new(inventory: Inventory, timeout: Duration) -> SharedInventory<SystemClock> {
+        SharedInventory::with_clock(inventory, timeout, SystemClock::new())
+    }
+}
+
+impl<C: Clock> SharedInventory<C> {
+    pub fn with_clock(inventory: Inventory, timeout: Duration, clock: C) -> SharedInventory<C> {
+        SharedInventory {
+            clock,
+            timeout,
+            state: Mutex::new(State {
+                available: inventory,
+                holds: HashMap::new(),
+                deadlines: BTreeSet::new(),
+                next_id: 0,
+                given: 0,
+            }),
+        }
+    }
+
+    // Locks the state, after cancelling the reservations that have timed out.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.expire(self.clock.now());
+        state
+    }
+
+    /// Reserves a shirt like `Inventory::giveaway` would choose it.
+    pub fn reserve(&self, user_preference: Option<ShirtColor>) -> Result<(ReservationId, Sku), ReservationError> {
+        self.reserve_with(&mut MostStocked, user_preference.as_slice())
+    }
+
+    /// Reserves the shirt `strategy` chooses; it stays reserved until confirmed, cancelled or timed out.
+    pub fn reserve_with(
+        &self,
+        strategy: &mut dyn Strategy,
+        preferences: &[ShirtColor],
+    ) -> Result<(ReservationId, Sku), ReservationError> {
+        let mut state = self.lock();
+        // A timeout too long to add to the time never runs out.
+        let expires = self.clock.now().checked_add(self.timeout).unwrap_or(Duration::MAX);
+        let sku = state.available.giveaway_with(strategy, preferences)?;
+        let id = ReservationId(state.next_id);
+        state.next_id += 1;
+        state.holds.insert(id, Hold { sku, expires });
+        state.deadlines.insert((expires, id));
+        Ok((id, sku))
+    }
+
+    /// Hands the reserved shirt over for good.
+    pub fn confirm(&self, id: ReservationId) -> Result<Sku, ReservationError> {
+        let mut state = self.lock();
+        let sku = state.release(id).ok_or(ReservationError::NotHeld(id))?;
+        state.given += 1;
+        Ok(sku)
+    }
+
+    /// Puts the reserved shirt back in stock.
+    pub fn cancel(&self, id: ReservationId) -> Result<Sku, ReservationError> {
+        let mut state = self.lock();
+        let sku = state.release(id).ok_or(ReservationError::NotHeld(id))?;
+        state.available.restock(sku, 1);
+        Ok(sku)
+    }
+
+    /// A copy of the shirts that can still be reserved.
+    pub fn available(&self) -> Inventory {
+        self.lock().available.clone()
+    }
+
+    /// How many shirts are reserved right now.
+    pub fn held(&self) -> usize {
+        self.lock().holds.len()
+    }
+
+    /// How many reservations have been confirmed.
+    pub fn given(&self) -> usize {
+        self.lock().given
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::shirts::fixtures::{crew, stock};
+    use crate::shirts::Size;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::thread;
+    use ShirtColor::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn reserved_shirts_are_out_of_stock_until_cancelled() {
+        let shared = SharedInventory::with_clock(stock(), TIMEOUT, ManualClock::new());
+
+        let (first, _) = shared.reserve(Some(Red)).unwrap();
+        let (red, _) = shared.reserve(Some(Red)).unwrap();
+        assert_eq!(shared.reserve(Some(Red)), Err(ReservationError::Giveaway(GiveawayError::OutOfStock(Red))));
+        assert_eq!((shared.held(), shared.available().total()), (2, 8));
+
+        assert_eq!(shared.cancel(red), Ok(crew(Red, Size::Small)));
+        assert_eq!(shared.cancel(red), Err(ReservationError::NotHeld(red)));
+        let (red, _) = shared.reserve(Some(Red)).unwrap();
+        assert_eq!(shared.confirm(red), Ok(crew(Red, Size::Small)));
+        assert_eq!(shared.confirm(red), Err(ReservationError::NotHeld(red)));
+        assert_eq!((shared.held(), shared.given(), shared.available().total()), (1, 1, 8));
+        assert_eq!(shared.cancel(first), Ok(crew(Red, Size::Small)));
+    }
+
+    #[test]
+    fn reservations_time_out() {
+        let clock = ManualClock::new();
+        let shared = SharedInventory::with_clock(stock(), TIMEOUT, &clock);
+        let (first, _) = shared.reserve(Some(Blue)).unwrap();
+        clock.advance(TIMEOUT / 2);
+        let (second, _) = shared.reserve(Some(Red)).unwrap();
+
+        clock.advance(TIMEOUT / 2);
+        assert_eq!(shared.confirm(first), Err(ReservationError::NotHeld(first)));
+        assert_eq!(shared.available().count(Blue), 5);
+        assert_eq!(shared.confirm(second), Ok(crew(Red, Size::Small)));
+    }
+
+    #[test]
+    fn timeouts_too_long_to_add_never_run_out() {
+        let clock = ManualClock::new();
+        clock.advance(TIMEOUT);
+        let shared = SharedInventory::with_clock(stock(), Duration::MAX, &clock);
+        let (red, _) = shared.reserve(Some(Red)).unwrap();
+        clock.advance(TIMEOUT);
+        assert_eq!(shared.confirm(red), Ok(crew(Red, Size::Small)));
+    }
+
+    struct Panicking;
+
+    impl Strategy for Panicking {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn choose(&mut self, _: &Inventory, _: &[ShirtColor]) -> Result<Sku, GiveawayError> {
+            panic!("the strategy broke")
+        }
+    }
+
+    #[test]
+    fn a_panicking_strategy_leaves_the_inventory_usable() {
+        let shared = SharedInventory::with_clock(stock(), TIMEOUT, ManualClock::new());
+        let (red, _) = shared.reserve(Some(Red)).unwrap();
+
+        let crashed = panic::catch_unwind(AssertUnwindSafe(|| shared.reserve_with(&mut Panicking, &[Blue])));
+        assert!(crashed.is_err());
+
+        assert_eq!((shared.held(), shared.available().total()), (1, 9));
+        assert_eq!(shared.confirm(red), Ok(crew(Red, Size::Small)));
+        assert!(shared.reserve(Some(Blue)).is_ok());
+    }
+
+    // Kiosks reserve, confirm, cancel and abandon reservations while time moves on under them.
+    // However they interleave, each shirt is given away at most once.
+    #[test]
+    fn many_kiosks_never_oversell() {
+        const KIOSKS: u64 = 16;
+        let clock = ManualClock::new();
+        let shared = SharedInventory::with_clock(stock(), Duration::from_millis(5), &clock);
+
+        let confirmed: Vec<Sku> = thread::scope(|scope| {
+            let kiosks: Vec<_> = (0..KIOSKS)
+                .map(|kiosk| {
+                    let (shared, clock) = (&shared, &clock);
+                    scope.spawn(move || {
+                        let mut rng = StdRng::seed_from_u64(kiosk);
+                        let mut confirmed = vec![];
+                        for _ in 0..200 {
+                            let preference = [None, Some(Red), Some(Blue)][rng.gen_range(0..3)];
+                            if let Ok((id, _)) = shared.reserve(preference) {
+                                clock.advance(Duration::from_millis(rng.gen_range(0..3)));
+                                match rng.gen_range(0..4) {
+                                    0 => {
+                                        let _ = shared.cancel(id);
+                                    }
+                                    1 => {} // walked away; the reservation times out
+                                    _ => confirmed.extend(shared.confirm(id)),
+                                }
+                            }
+                        }
+                        confirmed
+                    })
+                })
+                .collect();
+            kiosks.into_iter().flat_map(|kiosk| kiosk.join().unwrap()).collect()
+        });
+
+        clock.advance(Duration::from_millis(5));
+        let available = shared.available();
+        assert_eq!(shared.held(), 0);
+        assert_eq!(shared.given(), confirmed.len());
+        for color in [Red, Blue, Green] {
+            let (given, stocked) = (confirmed.iter().filter(|sku| sku.color == color).count(), stock().count(color));
+            assert!(given <= stocked, "{given} {color:?} shirts given away");
+            assert_eq!(given + available.count(color), stocked);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use super::{GiveawayError, Inventory, ShirtColor, Sku};
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/*
+    A Strategy decides which shirt a user gets. It sees the inventory and the user's ranked
+    colour preferences (best first, possibly none), and chooses a SKU that is in stock; the
+    inventory then takes that shirt out. Strategies may keep state between users, like whose
+    turn it is or a random number generator.
+ */
+pub trait Strategy {
+    fn name(&self) -> &str;
+
+    fn choose(&mut self, inventory: &Inventory, preferences: &[ShirtColor]) -> Result<Sku, GiveawayError>;
+}
+
+// The colours with shirts left, in declaration order.
+fn colors_in_stock(inventory: &Inventory) -> Vec<ShirtColor> {
+    let mut colors: Vec<ShirtColor> = inventory.tally().iter().map(|(color, _)| color).collect();
+    colors.sort();
+    colors
+}
+
+/// The user's first preference, or the most stocked colour if they have none. `giveaway` uses this.
+#[derive(Debug, Default)]
+pub struct MostStocked;
+
+impl Strategy for MostStocked {
+    fn name(&self) -> &str {
+        "most stocked"
+    }
+
+    /*
+        The or_else method on Option<T> is defined by the standard library. It takes one argument:
+        a closure without any arguments that returns an Option<T> (in this case Option<ShirtColor>).
+        If the Option<T> is the Some variant, or_else returns it as it is. If it is the None
+        variant, or_else calls the closure and returns what the closure returned. If the closure
+        had parameters, they would appear between the two vertical bars. The closure captures an
+        immutable reference to the inventory and passes it with the code we specify to or_else.
+        Functions, on the other hand, are not able to capture their environment in this way.
+     */
+    fn choose(&mut self, inventory: &Inventory, preferences: &[ShirtColor]) -> Result<Sku, GiveawayError> {
+        let color = preferences
+            .first()
+            .copied()
+            .or_else(|| inventory.most_stocked())
+            .ok_or(GiveawayError::SoldOut)?;
+        inventory.best_of(color).ok_or(GiveawayError::OutOfStock(color))
+    }
+}
+
+/// The first of the user's preferences that is in stock. Users without preferences get the
+/// most stocked colour; users whose preferences have all run out get an error naming them all.
+#[derive(Debug, Default)]
+pub struct RankedPreferences;
+
+impl Strategy for RankedPreferences {
+    fn name(&self) -> &str {
+        "ranked preferences"
+    }
+
+    fn choose(&mut self, inventory: &Inventory, preferences: &[ShirtColor]) -> Result<Sku, GiveawayError> {
+        if preferences.is_empty() {
+            return MostStocked.choose(inventory, preferences);
+        }
+        preferences
+            .iter()
+            .find_map(|color| inventory.best_of(*color))
+            .ok_or_else(|| GiveawayError::PreferencesOutOfStock(preferences.to_vec()))
+    }
+}
+
+/// Hands out the colours in stock in turn, whatever the users prefer.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    last: Option<ShirtColor>,
+}
+
+impl RoundRobin {
+    pub fn new() -> RoundRobin {
+        RoundRobin::default()
+    }
+}
+
+impl Strategy for RoundRobin {
+    fn name(&self) -> &str {
+        "round robin"
+    }
+
+    fn choose(&mut self, inventory: &Inventory, _: &[ShirtColor]) -> Result<Sku, GiveawayError> {
+        let colors = colors_in_stock(inventory);
+        // The next colour after the last one handed out, starting over after the last colour.
+        let color = colors
+            .iter()
+            .find(|color| self.last.is_some_and(|last| **color > last))
+            .or(colors.first())
+            .copied()
+            .ok_or(GiveawayError::SoldOut)?;
+        self.last = Some(color);
+        inventory.best_of(color).ok_or(GiveawayError::OutOfStock(color))
+    }
+}
+
+/// Picks a colour at random, whatever the users prefer, with a chance proportional to its weight.
+///
+/// Without weights, a colour's weight is its stock, so every shirt is as likely as any other.
+/// With weights, colours that aren't listed are never picked.
+pub struct Weighted<R: Rng> {
+    rng: R,
+    weights: Option<HashMap<ShirtColor, u32>>,
+}
+
+impl<R: Rng> Weighted<R> {
+    pub fn by_stock(rng: R) -> Weighted<R> {
+        Weighted { rng, weights: None }
+    }
+
+    pub fn new(rng: R, weights: &[(ShirtColor, u32)]) -> Weighted<R> {
+        Weighted { rng, weights: Some(weights.iter().copied().collect()) }
+    }
+}
+
+impl<R: Rng> Strategy for Weighted<R> {
+    fn name(&self) -> &str {
+        "weighted random"
+    }
+
+    fn choose(&mut self, inventory: &Inventory, _: &[ShirtColor]) -> Result<Sku, GiveawayError> {
+        let colors = colors_in_stock(inventory);
+        let weights = colors.iter().map(|color| match &self.weights {
+            Some(weights) => weights.get(color).copied().unwrap_or(0) as usize,
+            None => inventory.count(*color),
+        });
+        // Fails when no colour in stock has any weight: either nothing is left at all, or only
+        // colours that were given no weight.
+        let index = WeightedIndex::new(weights).map_err(|_| {
+            if colors.is_empty() {
+                GiveawayError::SoldOut
+            } else {
+                GiveawayError::NoEligibleColor
+            }
+        })?;
+        let color = colors[index.sample(&mut self.rng)];
+        inventory.best_of(color).ok_or(GiveawayError::OutOfStock(color))
+    }
+}
+
+/// Always gives away the most stocked SKU, across colours, sizes and styles, so the stock
+/// evens out. Users' preferences are ignored.
+#[derive(Debug, Default)]
+pub struct Balance;
+
+impl Strategy for Balance {
+    fn name(&self) -> &str {
+        "balance"
+    }
+
+    fn choose(&mut self, inventory: &Inventory, _: &[ShirtColor]) -> Result<Sku, GiveawayError> {
+        inventory.most_stocked().ok_or(GiveawayError::SoldOut)
+    }
+}
+
+/// `count` users with up to three different preferred colours each, drawn from `colors`.
+pub fn random_users(count: usize, colors: &[ShirtColor], seed: u64) -> Vec<Vec<ShirtColor>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| {
+            let preferences = rng.gen_range(0..=3.min(colors.len()));
+            colors.choose_multiple(&mut rng, preferences).copied().collect()
+        })
+        .collect()
+}
+
+/// What happened when a queue of users came for shirts.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub strategy: String,
+    pub users: usize,
+    /// Users who got a shirt.
+    pub served: usize,
+    /// Users who got their first preference.
+    pub first_choice: usize,
+    /// Users who got one of their preferences, first or not.
+    pub any_choice: usize,
+    pub given: BTreeMap<ShirtColor, usize>,
+    /// The inventory after the last user.
+    pub left: Inventory,
+}
+
+/// Gives every user in `users`, in order, a shirt from a copy of `inventory` chosen by `strategy`.
+pub fn simulate(strategy: &mut dyn Strategy, inventory: &Inventory, users: &[Vec<ShirtColor>]) -> Simulation {
+    let mut left = inventory.clone();
+    let mut simulation = Simulation {
+        strategy: String::from(strategy.name()),
+        users: users.len(),
+        served: 0,
+        first_choice: 0,
+        any_choice: 0,
+        given: BTreeMap::new(),
+        left: Inventory::new(),
+    };
+
+    for preferences in users {
+        if let Ok(sku) = left.giveaway_with(strategy, preferences) {
+            simulation.served += 1;
+            if preferences.first() == Some(&sku.color) {
+                simulation.first_choice += 1;
+            }
+            if preferences.contains(&sku.color) {
+                simulation.any_choice += 1;
+            }
+            *simulation.given.entry(sku.color).or_insert(0) += 1;
+        }
+    }
+    simulation.left = left;
+    simulation
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} of {} users served, {} got their first choice, {} one of their choices, {} shirts left",
+            self.strategy,
+            self.served,
+            self.users,
+            self.first_choice,
+            self.any_choice,
+            self.left.total()
+        )?;
+        for (color, count) in &self.given {
+            writeln!(f, "  {color:?}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shirts::fixtures::{crew, stock};
+    use crate::shirts::Size;
+    use ShirtColor::*;
+
+    fn colors(strategy: &mut dyn Strategy, inventory: &mut Inventory, users: usize) -> Vec<ShirtColor> {
+        (0..users).map(|_| inventory.giveaway_with(strategy, &[]).unwrap().color).collect()
+    }
+
+    #[test]
+    fn most_stocked_is_the_old_giveaway() {
+        let mut inventory = stock();
+        assert_eq!(inventory.giveaway_with(&mut MostStocked, &[Red, Green]), Ok(crew(Red, Size::Small)));
+        assert_eq!(inventory.giveaway_with(&mut MostStocked, &[Black, Red]), Err(GiveawayError::OutOfStock(Black)));
+        assert_eq!(inventory.giveaway(None), Ok(crew(Blue, Size::Medium)));
+    }
+
+    #[test]
+    fn ranked_preferences_fall_through_to_the_first_in_stock() {
+        let mut inventory = stock();
+        assert_eq!(inventory.giveaway_with(&mut RankedPreferences, &[Black, Green]), Ok(crew(Green, Size::Large)));
+        assert_eq!(
+            inventory.giveaway_with(&mut RankedPreferences, &[Black, Red, Black]),
+            Ok(crew(Red, Size::Small))
+        );
+        inventory.giveaway(Some(Red)).unwrap();
+        let error = inventory.giveaway_with(&mut RankedPreferences, &[Black, Red]).unwrap_err();
+        assert_eq!(error, GiveawayError::PreferencesOutOfStock(vec![Black, Red]));
+        assert_eq!(error.to_string(), "no Black or Red shirts left");
+        assert_eq!(inventory.giveaway_with(&mut RankedPreferences, &[]), Ok(crew(Blue, Size::Medium)));
+    }
+
+    #[test]
+    fn round_robin_takes_turns_and_skips_colours_that_run_out() {
+        let mut inventory = stock();
+        assert_eq!(
+            colors(&mut RoundRobin::new(), &mut inventory, 8),
+            vec![Red, Blue, Green, Red, Blue, Green, Blue, Green]
+        );
+    }
+
+    #[test]
+    fn balance_evens_out_the_skus() {
+        let mut inventory = stock();
+        assert_eq!(colors(&mut Balance, &mut inventory, 4), vec![Blue, Blue, Green, Red]);
+
+        assert_eq!(inventory.count(crew(Blue, Size::Medium)), 2);
+        assert_eq!(inventory.count(crew(Green, Size::Large)), 2);
+        assert_eq!(inventory.count(crew(Red, Size::Small)), 1);
+    }
+
+    #[test]
+    fn weighted_random_is_repeatable_and_respects_weights() {
+        let mut first = stock();
+        let mut second = stock();
+        assert_eq!(
+            colors(&mut Weighted::by_stock(StdRng::seed_from_u64(7)), &mut first, 10),
+            colors(&mut Weighted::by_stock(StdRng::seed_from_u64(7)), &mut second, 10)
+        );
+
+        let mut inventory = stock();
+        let mut green_only = Weighted::new(StdRng::seed_from_u64(7), &[(Green, 1), (Black, 5)]);
+        assert_eq!(colors(&mut green_only, &mut inventory, 3), vec![Green; 3]);
+        // Red and Blue shirts are left, but this giveaway only hands out Green and Black ones.
+        assert_eq!(inventory.giveaway_with(&mut green_only, &[]), Err(GiveawayError::NoEligibleColor));
+
+        let mut sold_out = Inventory::new();
+        assert_eq!(sold_out.giveaway_with(&mut green_only, &[]), Err(GiveawayError::SoldOut));
+        assert_eq!(sold_out.giveaway_with(&mut Weighted::by_stock(StdRng::seed_from_u64(7)), &[]), Err(GiveawayError::SoldOut));
+    }
+
+    #[test]
+    fn simulates_a_queue_of_users() {
+        let inventory = stock();
+        let users = random_users(40, &[Red, Blue, Green, Black], 3);
+        let ranked = simulate(&mut RankedPreferences, &inventory, &users);
+        let balance = simulate(&mut Balance, &inventory, &users);
+
+        assert_eq!(inventory.total(), 10);
+        assert_eq!((ranked.served, balance.served), (10, 10));
+        assert_eq!(ranked.left.total(), 0);
+        assert_eq!(ranked.given.values().sum::<usize>(), 10);
+        assert!(ranked.any_choice >= balance.any_choice);
+        assert!(ranked.to_string().starts_with("ranked preferences: 10 of 40 users served"));
+    }
+}
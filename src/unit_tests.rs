@@ -87,6 +87,17 @@ mod tests {
         Guess::new(200);
     }
 
+    #[test]
+    fn try_new_rejects_instead_of_panicking() {
+        assert_eq!(Guess::try_new(50).map(|guess| guess.value()), Ok(50));
+        assert_eq!(
+            Guess::try_new(0).map(|guess| guess.value()),
+            Err(GuessError::OutOfRange { value: 0, min: 1, max: 100 })
+        );
+        assert!(matches!(" seven\n".parse::<Guess>(), Err(GuessError::Parse { input, .. }) if input == "seven"));
+        assert_eq!(" 7\n".parse::<Guess>().map(|guess| guess.value()), Ok(7));
+    }
+
     /*
         Writing tests so they return a Result<T, E> enables you to use the question mark operator
         in the body of tests, which can be a convenient way to write tests that should fail if any
@@ -0,0 +1,138 @@
+/*
+    The game engine takes its random number generator from the caller, so seeding it makes
+    every game reproducible, and the front-end reads and writes any BufRead/Write pair,
+    so a whole interactive session can be scripted with in-memory buffers.
+ */
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rust_book::guessing_game::*;
+
+// Binary search through the engine, returning every guess and its feedback.
+fn solve(game: &mut Game) -> Vec<(i32, Feedback)> {
+    let (mut low, mut high) = (*game.range().start(), *game.range().end());
+    let mut history = vec![];
+
+    loop {
+        let guess = low + (high - low) / 2;
+        let feedback = game.guess(guess).unwrap();
+        history.push((guess, feedback));
+
+        match feedback {
+            Feedback::TooSmall => low = guess + 1,
+            Feedback::TooBig => high = guess - 1,
+            Feedback::Correct => return history,
+        }
+    }
+}
+
+#[test]
+fn same_seed_same_game() {
+    let first = solve(&mut Game::new(&mut StdRng::seed_from_u64(7), 1..=100));
+    let second = solve(&mut Game::new(&mut StdRng::seed_from_u64(7), 1..=100));
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn scripted_session_through_the_front_end() {
+    let guesses = solve(&mut Game::new(&mut StdRng::seed_from_u64(2024), 1..=100));
+    let input: String = guesses.iter().map(|(guess, _)| format!("{guess}\n")).collect();
+
+    let mut game = Game::new(&mut StdRng::seed_from_u64(2024), 1..=100);
+    let mut output = vec![];
+    assert_eq!(play(&mut game, input.as_bytes(), &mut output).unwrap(), Outcome::Won);
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("Guess the number!\nThe number is between 1 and 100.\nPlease input your guess.\n"));
+    assert!(output.ends_with("You win!\n"));
+    assert_eq!(output.matches("Please input your guess.").count(), guesses.len());
+    assert_eq!(game.attempts() as usize, guesses.len());
+}
+
+#[test]
+fn running_out_of_input_is_an_error() {
+    let mut game = Game::new(&mut StdRng::seed_from_u64(1), 1..=100);
+
+    assert!(play(&mut game, "".as_bytes(), vec![]).is_err());
+}
+
+#[test]
+fn invalid_input_is_reported_and_the_game_goes_on() {
+    let guesses = solve(&mut Game::new(&mut StdRng::seed_from_u64(99), 1..=100));
+    let mut input = String::from("forty-two\n0\n\n");
+    input.extend(guesses.iter().map(|(guess, _)| format!("{guess}\n")));
+
+    let mut game = Game::new(&mut StdRng::seed_from_u64(99), 1..=100);
+    let mut output = vec![];
+    play(&mut game, input.as_bytes(), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("`forty-two` is not a number.\n"));
+    assert!(output.contains("The guess must be between 1 and 100, got 0.\n"));
+    assert!(output.contains("Please type a number.\n"));
+    assert!(output.ends_with("You win!\n"));
+    // Invalid lines never reach the engine, so they don't count as attempts.
+    assert_eq!(game.attempts() as usize, guesses.len());
+}
+
+#[test]
+fn running_out_of_attempts_loses_the_game() {
+    let mut game = Game::with_settings(&mut StdRng::seed_from_u64(5), Difficulty::Hard);
+    // Ten guesses of 1000 can only win if the secret is 1000, which seed 5 does not pick.
+    let input = "1000\n".repeat(11);
+    let mut output = vec![];
+
+    assert_eq!(play(&mut game, input.as_bytes(), &mut output).unwrap(), Outcome::Lost);
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("The number is between 1 and 1000.\nYou have 10 attempts.\n"));
+    assert_eq!(output.matches("Too big.").count(), 10);
+    let secret = game.reveal().unwrap();
+    assert!(output.ends_with(&format!("You lose! The number was {secret}.\n")));
+    assert_eq!(game.remaining_attempts(), Some(0));
+}
+
+#[test]
+fn guesses_are_validated_against_the_game_range() {
+    let game = Game::with_settings(&mut StdRng::seed_from_u64(5), Difficulty::Easy);
+
+    assert!(game.validate("50").is_ok());
+    assert_eq!(
+        game.validate("51").map(|guess| guess.value()),
+        Err(GuessError::OutOfRange { value: 51, min: 1, max: 50 })
+    );
+    assert_eq!(game.reveal(), None);
+}
+
+#[test]
+fn the_engine_rejects_guesses_outside_the_range_and_after_the_game() {
+    let mut game = Game::with_settings(&mut StdRng::seed_from_u64(5), Settings::new(200..=300, Some(1)));
+
+    assert_eq!(game.validate("250").map(|guess| guess.value()), Ok(250));
+    assert_eq!(game.guess(100), Err(GuessError::OutOfRange { value: 100, min: 200, max: 300 }));
+    assert_eq!(game.attempts(), 0);
+
+    let unlimited = Settings::new(200..=300, None);
+    let (secret, _) = *solve(&mut Game::with_settings(&mut StdRng::seed_from_u64(5), unlimited)).last().unwrap();
+    let wrong = if secret == 200 { 201 } else { 200 };
+    assert!(game.guess(wrong).is_ok());
+    assert_eq!(game.outcome(), Some(Outcome::Lost));
+    assert_eq!(game.guess(wrong), Err(GuessError::GameOver));
+    assert_eq!(game.attempts(), 1);
+}
+
+#[test]
+fn plays_in_the_locale_of_the_settings() {
+    let settings = Settings::new(1..=100, Some(2)).with_locale(Locale::German);
+    let mut game = Game::with_settings(&mut StdRng::seed_from_u64(3), settings);
+    let mut output = vec![];
+    let outcome = play(&mut game, "abc\n0\n1\n100\n".as_bytes(), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(outcome, Outcome::Lost);
+    assert!(output.starts_with("Errate die Zahl!\nDie Zahl liegt zwischen 1 und 100.\nDu hast 2 Versuche.\n"));
+    assert!(output.contains("`abc` ist keine Zahl.\n"));
+    assert!(output.contains("Der Tipp muss zwischen 1 und 100 liegen, nicht 0.\n"));
+    assert!(output.contains("Zu klein. Versuch eine größere Zahl!\n"));
+    assert!(output.ends_with(&format!("Verloren! Die Zahl war {}.\n", game.reveal().unwrap())));
+}
@@ -0,0 +1,81 @@
+/*
+    Runs a real race over TCP on 127.0.0.1: the server and every client get their own thread,
+    and the clients talk to the server through sockets exactly like remote players would.
+ */
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rust_book::guessing_game::network::*;
+use rust_book::guessing_game::solver::{BinarySearch, Linear};
+use rust_book::guessing_game::{solve, Game, Locale, Settings};
+use std::thread;
+
+#[test]
+fn players_race_for_the_same_number() {
+    let game = Game::new(&mut StdRng::seed_from_u64(36), 1..=100);
+    let server = Server::bind("127.0.0.1:0", game).unwrap();
+    let address = server.local_addr().unwrap();
+    let server = thread::spawn(move || server.run(3).unwrap());
+
+    let binary = thread::spawn(move || join(address, &mut BinarySearch::new()).unwrap());
+    let linear = thread::spawn(move || join(address, &mut Linear::new()).unwrap());
+    // A person who types two invalid lines and then walks away.
+    let human = thread::spawn(move || {
+        let mut output = vec![];
+        let outcome = join_interactive(address, Locale::English, "abc\n0\n".as_bytes(), &mut output);
+        (outcome.ok(), String::from_utf8(output).unwrap())
+    });
+
+    let result = server.join().unwrap();
+    let outcomes = [binary.join().unwrap(), linear.join().unwrap()];
+    let (human_outcome, human_output) = human.join().unwrap();
+
+    let winner = result.winner.expect("one of the solvers should have won");
+    let secret_number = result.secret_number.unwrap();
+    assert!((1..=100).contains(&secret_number));
+
+    // Exactly one solver won; everybody else heard who won and what the number was.
+    let won = RaceOutcome::Won { secret_number };
+    let lost = RaceOutcome::Lost { winner, secret_number };
+    assert_eq!(outcomes.iter().filter(|outcome| **outcome == won).count(), 1);
+    assert_eq!(outcomes.iter().filter(|outcome| **outcome == lost).count(), 1);
+    assert!(human_outcome.is_none() || human_outcome == Some(lost));
+
+    // Invalid lines are answered (unless the race ended first) but never count as attempts.
+    assert!(human_output.contains("Guess the number!\nThe number is between 1 and 100.\n"));
+    assert!(!human_output.contains("You win!"));
+    let guessed = result.attempts.iter().filter(|attempts| **attempts > 0).count();
+    assert!((1..=2).contains(&guessed));
+}
+
+#[test]
+fn everybody_loses_when_the_shared_attempts_run_out() {
+    let settings = Settings::new(1..=100, Some(3));
+    let mut preview = Game::with_settings(&mut StdRng::seed_from_u64(36), settings.clone());
+    solve(&mut preview, &mut BinarySearch::new()).unwrap();
+    let secret_number = preview.reveal().unwrap();
+    // Two players counting up from 1 can't reach the number in three guesses between them.
+    assert!(secret_number > 3);
+
+    let game = Game::with_settings(&mut StdRng::seed_from_u64(36), settings);
+    let server = Server::bind("127.0.0.1:0", game).unwrap();
+    let address = server.local_addr().unwrap();
+    let server = thread::spawn(move || server.run(2).unwrap());
+
+    let linear = thread::spawn(move || join(address, &mut Linear::new()).unwrap());
+    let human = thread::spawn(move || {
+        let mut output = vec![];
+        let outcome = join_interactive(address, Locale::German, "1\n2\n3\n".as_bytes(), &mut output).unwrap();
+        (outcome, String::from_utf8(output).unwrap())
+    });
+
+    let result = server.join().unwrap();
+    let lost = RaceOutcome::OutOfAttempts { secret_number };
+    assert_eq!(linear.join().unwrap(), lost);
+    let (human_outcome, human_output) = human.join().unwrap();
+    assert_eq!(human_outcome, lost);
+    assert!(human_output.ends_with(&format!("Niemand hat sie rechtzeitig erraten. Die Zahl war {secret_number}.\n")));
+
+    assert_eq!(result.winner, None);
+    assert_eq!(result.secret_number, Some(secret_number));
+    assert_eq!(result.attempts.iter().sum::<u32>(), 3);
+}